@@ -0,0 +1,102 @@
+//! Imports a CSV list of todos into a project, the same way as the import page does.
+//!
+//! > project_loader --project <uuid> --list todos.csv [--dry-run]
+//! It takes `DB_WRITE_URL` from the environment or `.env`. Nothing is inserted when any
+//! row is invalid, rows already present in the project are skipped.
+//! Exits with 1 when the list has invalid rows and with 2 when it could not be imported.
+
+use std::env;
+use std::fs;
+use std::process;
+
+use clap::{App, Arg};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use ecslib::modules::project::data::Project;
+use ecslib::modules::project::import::{import, ImportReport};
+use ecspg::schema::projects;
+
+fn build_cli() -> App<'static, 'static> {
+    App::new("project_loader")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Imports todos from a csv with name,phone,email,description columns")
+        .arg(
+            Arg::with_name("project")
+                .long("project")
+                .help("The uuid of the project")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("list")
+                .long("list")
+                .help("The csv file, with or without a header row")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Only checks the list, nothing is inserted"),
+        )
+}
+
+fn print_report(report: &ImportReport) {
+    if report.header {
+        println!("Header row found");
+    }
+    for err in &report.errors {
+        eprintln!("line {}: {}", err.line, err.message);
+    }
+    for row in &report.duplicates {
+        println!(
+            "line {}: {} is already present, skipped",
+            row.line, row.title
+        );
+    }
+    println!(
+        "{} valid, {} duplicates, {} errors, {} imported",
+        report.rows.len(),
+        report.duplicates.len(),
+        report.errors.len(),
+        report.imported
+    );
+}
+
+/// Stops on a mistake of the operator or an unreachable database, the message leaves the
+/// database url out since it holds the password
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+fn main() {
+    let matches = build_cli().get_matches();
+    let project_uuid = matches
+        .value_of("project")
+        .unwrap()
+        .parse::<::uuid::Uuid>()
+        .unwrap_or_else(|e| fail(format!("Not a project uuid: {}", e)));
+    let list = matches.value_of("list").unwrap();
+    let input = fs::read(list).unwrap_or_else(|e| fail(format!("Error reading {}: {}", list, e)));
+
+    dotenv::dotenv().ok();
+    let database_url =
+        env::var("DB_WRITE_URL").unwrap_or_else(|_| fail("DB_WRITE_URL must be set".to_string()));
+    let conn = PgConnection::establish(&database_url)
+        .unwrap_or_else(|e| fail(format!("Error connecting to DB_WRITE_URL: {}", e)));
+
+    let project: Project = projects::table
+        .filter(projects::uuid.eq(project_uuid))
+        .filter(projects::deleted_at.is_null())
+        .first(&conn)
+        .unwrap_or_else(|e| fail(format!("No project {}: {}", project_uuid, e)));
+
+    let report = import(&conn, &project, &input, matches.is_present("dry-run"), None)
+        .unwrap_or_else(|e| fail(format!("Import failed: {}", e)));
+    print_report(&report);
+    if !report.is_valid() {
+        process::exit(1);
+    }
+}
@@ -7,6 +7,8 @@ extern crate serde_derive;
 extern crate diesel;
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate validator_derive;
 
 use actix_diesel_actor as db;
 use ecspg::schema;
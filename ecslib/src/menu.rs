@@ -2,25 +2,27 @@ use crate::modules::navigation::{Link, Permission};
 
 pub fn default_top_menu() -> Vec<Link> {
     let mut lnk = Vec::new();
-    // let teams_link = Link {
-    //     active: false,
-    //     children: None,
-    //     clearance: Permission::Browse,
-    //     icon: "fa-hourglass-half".to_string(),
-    //     url: "/org/select".to_string(),
-    //     visual: "Teams".to_string(),
-    // };
-    // lnk.push(teams_link);
+    let teams_link = Link {
+        active: false,
+        children: None,
+        clearance: Permission::Browse,
+        icon: "fa-hourglass-half".to_string(),
+        url: "/team/select".to_string(),
+        visual: "Teams".to_string(),
+    };
+    lnk.push(teams_link);
 
     lnk.push(Link::new("Project list", "/project/list"));
     lnk.push(Link::new("Project todos", "/project/todolist"));
+    lnk.push(Link::new("My todos", "/todo/mine"));
+    lnk.push(Link::new("Overdue todos", "/todo/overdue"));
 
     let myacc_link = Link {
         active: false,
         children: None,
         clearance: Permission::Browse,
         icon: "fa-hourglass-start".to_string(),
-        url: "".to_string(),
+        url: "/user/settings".to_string(),
         visual: "My Account".to_string(),
     };
     lnk.push(myacc_link);
@@ -49,25 +51,26 @@ pub fn default_menu() -> Vec<Link> {
     // lnk.push(Link::new("Style guide","/"));
     // lnk.push(Link::new("Email builder*","/email/build/0"));
 
-    // let mut org_links = Vec::new();
-    // org_links.push(Link::new("Switch", "/org/select"));
-    // org_links.push(Link::new("List", "/org/list"));
-    // org_links.push(Link::new("Add", "/org/add"));
-    // org_links.push(Link::new("Dashboard*", "/org/dashboard"));
-    // org_links.push(Link::new("Edit**", "#"));
-    // let org_link = Link {
-    //     active: false,
-    //     children: Some(org_links),
-    //     clearance: Permission::Browse,
-    //     icon: "fa-gopuram".to_string(),
-    //     url: "".to_string(),
-    //     visual: "Team".to_string(),
-    // };
-    // lnk.push(org_link);
+    let mut org_links = Vec::new();
+    org_links.push(Link::new("Switch", "/team/select"));
+    org_links.push(Link::new("List", "/team/list"));
+    org_links.push(Link::new("Add", "/team/add"));
+    org_links.push(Link::new("Dashboard", "/team/dashboard"));
+    let org_link = Link {
+        active: false,
+        children: Some(org_links),
+        clearance: Permission::Browse,
+        icon: "fa-gopuram".to_string(),
+        url: "".to_string(),
+        visual: "Team".to_string(),
+    };
+    lnk.push(org_link);
 
     let mut project_links = Vec::new();
     project_links.push(Link::new("List", "/project/list"));
     project_links.push(Link::new("Add", "/project/add"));
+    project_links.push(Link::new("Templates", "/project/templates"));
+    project_links.push(Link::new("Trash", "/project/trash"));
     // project_links.push(Link::new("todo*", "/project/todo/0"));
     // project_links.push(Link::new("todo Register*", "/project/register/0"));
     // project_links.push(Link::new("View*", "/project/1"));
@@ -0,0 +1,107 @@
+use actix_web::middleware::identity::RequestIdentity;
+use actix_web::{Error, HttpRequest, HttpResponse, Query};
+
+use crate::db::AppState;
+use crate::modules::meta::default_meta;
+use crate::modules::navigation::{Permission, PermissionSet};
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
+use crate::render::Failure;
+use crate::utils::http_ok;
+
+use super::{archived_entries, grants, is_admin, resolve, Grant};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ExplainParams {
+    pub user: Option<String>,
+    pub acid: Option<i64>,
+    pub format: Option<String>,
+}
+
+/// Everything which makes up the effective `PermissionSet` of a user on an access control entry
+#[derive(Serialize, Debug, Clone)]
+pub struct Explanation {
+    pub user: UserMeta,
+    pub access_control_id: i64,
+    pub grants: Vec<Grant>,
+    /// The entry belongs to an archived team, its grants beside browse and read are void
+    pub archived: bool,
+    pub permission: PermissionSet,
+    pub missing: Vec<Permission>,
+}
+impl Explanation {
+    /// The permission comes from `resolve`, as for `PermissionCheck`, the grants and the archive
+    /// state are loaded besides to tell where it comes from
+    fn build(req: &HttpRequest<AppState>, user: UserMeta, acid: i64) -> Option<Self> {
+        let permission = resolve(req, user.user_id)
+            .ok()?
+            .map()
+            .get(&acid)
+            .cloned()
+            .unwrap_or_else(PermissionSet::deny);
+        let grs: Vec<Grant> = grants(req, user.user_id)
+            .ok()?
+            .into_iter()
+            .filter(|gr| gr.access_control_id == acid)
+            .collect();
+        let archived = archived_entries(req).ok()?.contains(&acid);
+        let missing = permission.missing();
+        Some(Explanation {
+            user,
+            access_control_id: acid,
+            grants: grs,
+            archived,
+            permission,
+            missing,
+        })
+    }
+}
+
+fn index_render(
+    params: &ExplainParams,
+    expl: &Option<Explanation>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let cnt = ructe_block_res!(crate::templates::access::explain, params, expl)?;
+    let meta = default_meta("Permission explanation");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+/// Admin page answering "why can't I?" for a user and an access control entry
+pub fn index(
+    (req, params): (HttpRequest<AppState>, Query<ExplainParams>),
+) -> Result<HttpResponse, Error> {
+    let admin = match req.identity() {
+        Some(mail) => match UserMeta::load(&req, mail) {
+            Ok(usr_meta) => is_admin(&req, usr_meta.user_id),
+            Err(_) => false,
+        },
+        None => false,
+    };
+    if !admin {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let params = params.into_inner();
+    let mut expl = None;
+    if let (Some(mail), Some(acid)) = (params.user.clone(), params.acid) {
+        if let Ok(usr_meta) = UserMeta::load(&req, mail) {
+            expl = Explanation::build(&req, usr_meta, acid);
+        }
+    }
+    if params.format.as_ref().map(String::as_str) == Some("json") {
+        return match expl {
+            Some(expl) => Ok(HttpResponse::Ok().json(expl)),
+            None => Ok(HttpResponse::NotFound().finish()),
+        };
+    }
+    http_ok(index_render(&params, &expl, &req.impersonating()))
+}
@@ -1,4 +1,5 @@
 pub mod control;
+pub mod explain;
 pub mod group;
 pub mod group_member;
 pub mod key;
@@ -11,17 +12,20 @@ use actix_web::middleware::identity::RequestIdentity;
 use actix_web::middleware::{Middleware, Started};
 use actix_web::{FromRequest, HttpRequest, HttpResponse};
 
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use futures::future::Future;
 
 use crate::db::{AppState, DbExecutorError, SQuery, WQuery};
 use crate::modules::navigation::PermissionSet;
+use crate::modules::user::impersonate::RequestImpersonation;
 use crate::modules::user::UserMeta;
 
 use crate::schema::access_control::dsl::*;
 use crate::schema::access_group_members::dsl::*;
 use crate::schema::access_groups::dsl::*;
 use crate::schema::access_rules::dsl::*;
+use crate::schema::{access_keys, teams};
 use control::AccessControl;
 
 pub trait AddAllowed {
@@ -76,6 +80,165 @@ pub(crate) fn access_control_entry_with_api_key(
     Err(DbExecutorError::Unknown)
 }
 
+/// Where a single access type granted to a user comes from
+#[derive(Debug, Clone, Serialize)]
+pub enum GrantSource {
+    /// An `access_rules` row of a group the user is a member of
+    Rule {
+        rule_id: i64,
+        group_id: i64,
+        group_name: String,
+    },
+    /// A not yet expired `access_keys` row issued to the user
+    Key {
+        key_id: i64,
+        key: String,
+        reason: String,
+        expiry: DateTime<Utc>,
+    },
+}
+
+impl GrantSource {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GrantSource::Rule { .. } => "Group rule",
+            GrantSource::Key { .. } => "Access key",
+        }
+    }
+    pub fn describe(&self) -> String {
+        match self {
+            GrantSource::Rule {
+                rule_id,
+                group_id,
+                group_name,
+            } => format!("group {} (#{}), rule #{}", group_name, group_id, rule_id),
+            GrantSource::Key {
+                key_id,
+                key,
+                reason,
+                expiry,
+            } => format!(
+                "{} key #{} until {}: {}",
+                key,
+                key_id,
+                expiry.to_rfc3339(),
+                reason
+            ),
+        }
+    }
+}
+
+/// One access type on one access control entry, together with its origin
+#[derive(Debug, Clone, Serialize)]
+pub struct Grant {
+    pub access_control_id: i64,
+    pub access_type: String,
+    pub source: GrantSource,
+}
+
+/// Collects every grant of a user, this is what `PermissionCheck` is built from
+pub fn grants(req: &HttpRequest<AppState>, usr_id: i64) -> Result<Vec<Grant>, DbExecutorError> {
+    let query = access_rules
+        .inner_join(access_groups.inner_join(access_group_members))
+        .filter(user_id.eq(usr_id))
+        .select((
+            crate::schema::access_rules::id,
+            crate::schema::access_rules::access_control_id,
+            crate::schema::access_rules::access_type,
+            crate::schema::access_groups::id,
+            name,
+        ));
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<(i64, i64, String, i64, String)>,
+    };
+    let rules = req.state().rdb.send(sel).wait()??;
+    let mut res: Vec<Grant> = rules
+        .into_iter()
+        .map(|(rule_id, acid, tp, group_id, group_name)| Grant {
+            access_control_id: acid,
+            access_type: tp,
+            source: GrantSource::Rule {
+                rule_id,
+                group_id,
+                group_name,
+            },
+        })
+        .collect();
+
+    // a key opens the access control entry it was issued for and nothing else
+    let query = access_keys::table
+        .filter(access_keys::user_id.eq(usr_id))
+        .filter(access_keys::expiry.gt(Utc::now()))
+        .select((
+            access_keys::id,
+            access_keys::access_control_id,
+            access_keys::key,
+            access_keys::access_type,
+            access_keys::reason,
+            access_keys::expiry,
+        ));
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<(i64, i64, String, String, String, DateTime<Utc>)>,
+    };
+    let keys = req.state().rdb.send(sel).wait()??;
+    res.extend(
+        keys.into_iter()
+            .map(|(key_id, acid, module, tp, reason, expiry)| Grant {
+                access_control_id: acid,
+                access_type: tp,
+                source: GrantSource::Key {
+                    key_id,
+                    key: module,
+                    reason,
+                    expiry,
+                },
+            }),
+    );
+    Ok(res)
+}
+
+/// Name of the access group whose members can administer the whole service
+pub const ADMIN_GROUP: &str = "admin";
+
+/// Whether the user is a member of the `ADMIN_GROUP`
+pub fn is_admin(req: &HttpRequest<AppState>, usr_id: i64) -> bool {
+    let query = access_group_members
+        .inner_join(access_groups)
+        .filter(user_id.eq(usr_id))
+        .filter(name.eq(ADMIN_GROUP))
+        .select(crate::schema::access_group_members::id);
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<i64>,
+    };
+    match req.state().rdb.send(sel).wait() {
+        Ok(Ok(members)) => !members.is_empty(),
+        _ => false,
+    }
+}
+
+/// Access control entries of archived teams, they can only be browsed and read
+fn archived_entries(req: &HttpRequest<AppState>) -> Result<Vec<i64>, DbExecutorError> {
+    let query = teams::table
+        .filter(teams::archived_at.is_not_null())
+        .select(teams::access_control_id);
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<i64>,
+    };
+    Ok(req.state().rdb.send(sel).wait()??)
+}
+
+/// The effective permissions of the user: what the grants add up to, with the entries of
+/// archived teams kept to browsing and reading
+pub fn resolve(req: &HttpRequest<AppState>, usr_id: i64) -> Result<PermissionMap, DbExecutorError> {
+    let mut pmap = PermissionMap::from_grants(&grants(req, usr_id)?);
+    pmap.restrict_to_read(&archived_entries(req)?);
+    Ok(pmap)
+}
+
 pub struct PermissionCheck;
 impl Middleware<AppState> for PermissionCheck {
     fn start(&self, req: &HttpRequest<AppState>) -> actix_web::Result<Started> {
@@ -88,44 +251,12 @@ impl Middleware<AppState> for PermissionCheck {
 }
 impl PermissionCheck {
     fn from_request(req: &HttpRequest<AppState>) -> Option<PermissionMap> {
-        if let Some(mail) = req.identity() {
+        if let Some(mail) = req.effective_identity() {
             if let Ok(usr_meta) = UserMeta::load(req, mail) {
-                let query = access_rules
-                    .inner_join(access_groups.inner_join(access_group_members))
-                    .filter(user_id.eq(usr_meta.user_id))
-                    .select((
-                        crate::schema::access_rules::access_control_id,
-                        crate::schema::access_rules::access_type,
-                    ));
-                let sel = SQuery {
-                    select: query,
-                    phantom: PhantomData::<(i64, String)>,
-                };
-                let access = req.state().rdb.send(sel).wait().unwrap().unwrap();
-                let accmap: HashMap<_, _> = access.clone().into_iter().collect();
-
-                let mut permmap: HashMap<i64, PermissionSet> = HashMap::new();
-                for key in accmap.keys() {
-                    let mut types = Vec::new();
-                    for perm in &access {
-                        if perm.0.eq(key) {
-                            types.push(perm.1.clone());
-                        }
-                    }
-                    let mut pm = PermissionSet::deny();
-                    for tp in types {
-                        match tp.as_ref() {
-                            "browse" => pm.browse = true,
-                            "read" => pm.browse = true,
-                            "edit" => pm.browse = true,
-                            "add" => pm.browse = true,
-                            "delete" => pm.browse = true,
-                            _ => (),
-                        }
-                    }
-                    permmap.insert(*key, pm);
+                match resolve(req, usr_meta.user_id) {
+                    Ok(pmap) => return Some(pmap),
+                    Err(e) => error!("Permission resolution failed: {:?}", e),
                 }
-                return Some(PermissionMap(permmap));
             }
         }
         None
@@ -164,4 +295,22 @@ impl PermissionMap {
     pub fn map(&self) -> &HashMap<i64, PermissionSet> {
         &self.0
     }
+    pub fn from_grants(grs: &[Grant]) -> Self {
+        let mut permmap: HashMap<i64, PermissionSet> = HashMap::new();
+        for gr in grs {
+            permmap
+                .entry(gr.access_control_id)
+                .or_insert_with(PermissionSet::deny)
+                .grant(&gr.access_type);
+        }
+        PermissionMap(permmap)
+    }
+    /// Drops every permission beside browse and read on the given entries
+    pub fn restrict_to_read(&mut self, acids: &[i64]) {
+        for acid in acids {
+            if let Some(pset) = self.0.get_mut(acid) {
+                pset.restrict_to_read();
+            }
+        }
+    }
 }
@@ -0,0 +1,111 @@
+// JSON endpoints for scripts and integrations. They are authenticated with the api keys of a
+// team, sent as `Authorization: Bearer <key>`, instead of a login.
+use actix_web::http::header;
+use actix_web::{Error, HttpRequest, HttpResponse, Json};
+
+use diesel::prelude::*;
+use futures::future::Future;
+use std::marker::PhantomData;
+
+use crate::db::{AppState, SQuery};
+use crate::modules::project::data::Project;
+use crate::modules::project::live::{self, TodoEvent};
+use crate::modules::project::todo::{set_completion, Todo};
+use crate::modules::team::keys::{verify, ApiKey, ApiScope};
+use crate::schema::todos;
+use crate::utils::write_conn;
+
+/// The raw key of the request, if it carries one
+fn bearer(req: &HttpRequest<AppState>) -> Option<String> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    if value.len() > 7 && value[..7].eq_ignore_ascii_case("bearer ") {
+        Some(value[7..].trim().to_owned())
+    } else {
+        None
+    }
+}
+
+/// The key of the request when it is active and allows `required`, or the answer to give
+fn authorized(req: &HttpRequest<AppState>, required: ApiScope) -> Result<ApiKey, HttpResponse> {
+    let raw = match bearer(req) {
+        Some(raw) => raw,
+        None => {
+            return Err(HttpResponse::Unauthorized()
+                .header(header::WWW_AUTHENTICATE, "Bearer")
+                .finish())
+        }
+    };
+    verify(req, &raw, required).ok_or_else(|| {
+        HttpResponse::Unauthorized()
+            .header(header::WWW_AUTHENTICATE, "Bearer error=\"invalid_token\"")
+            .finish()
+    })
+}
+
+/// The project from the path, among the projects of the team of the key
+fn keyed_project(req: &HttpRequest<AppState>, ak: &ApiKey) -> Option<Project> {
+    let pid = req.match_info().get("id")?.parse::<i64>().ok()?;
+    Project::load(req, ak.team_id, pid).ok()
+}
+
+/// The todos of a project in their manual order, needs a `read` key
+pub fn todos(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let ak = match authorized(req, ApiScope::Read) {
+        Ok(ak) => ak,
+        Err(resp) => return Ok(resp),
+    };
+    let project = match keyed_project(req, &ak) {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let query = todos::table
+        .filter(todos::project_id.eq(project.uuid))
+        .filter(todos::deleted_at.is_null())
+        .order((todos::position.asc(), todos::id.asc()));
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<Todo>,
+    };
+    match req.state().rdb.send(select).wait() {
+        Ok(Ok(list)) => Ok(HttpResponse::Ok().json(list)),
+        _ => Ok(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CompletionParams {
+    completed: bool,
+}
+/// Completes or reopens a todo, needs a `toggle` key. Answers with the todo, or with 409 when
+/// its checklist decides the completion.
+pub fn toggle(
+    (req, form): (HttpRequest<AppState>, Json<CompletionParams>),
+) -> Result<HttpResponse, Error> {
+    let ak = match authorized(&req, ApiScope::Toggle) {
+        Ok(ak) => ak,
+        Err(resp) => return Ok(resp),
+    };
+    let project = match keyed_project(&req, &ak) {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let aid = match req.match_info().get("aid").map(str::parse::<i64>) {
+        Some(Ok(aid)) => aid,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let conn = write_conn(&req)?;
+    // no user stands behind a key, the history records the change without one
+    match set_completion(&conn, project.uuid, aid, form.completed, None) {
+        Ok(Some(updated)) => {
+            for todo in updated.iter() {
+                live::publish(TodoEvent::toggled(todo));
+            }
+            Ok(HttpResponse::Ok().json(updated))
+        }
+        Ok(None) => Ok(HttpResponse::Conflict().finish()),
+        Err(e) => {
+            error!("Toggling todo {} with key {} failed: {:?}", aid, ak.id, e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
@@ -8,10 +8,11 @@ use actix_web::ws::{handshake, WebsocketContext, WsStream};
 use actix_web::{ws, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, Path};
 
 use crate::db::AppState;
+use crate::modules::user::impersonate::RequestImpersonation;
 use crate::render::Failure;
 use crate::utils::http_ok;
 
-fn index_render() -> Result<String, Failure> {
+fn index_render(impersonating: &Option<String>) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let links = crate::menu::default_menu();
     let list = ructe_block_res!(crate::templates::email::build)?;
@@ -22,12 +23,13 @@ fn index_render() -> Result<String, Failure> {
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
 
-pub fn index(_req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
-    http_ok(index_render())
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    http_ok(index_render(&req.impersonating()))
 }
 
 fn save_str_file(file: String, json: &str) -> std::io::Result<()> {
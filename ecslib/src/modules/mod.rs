@@ -1,7 +1,9 @@
 pub mod access;
+pub mod api;
 pub mod email;
 pub mod meta;
 pub mod navigation;
 pub mod project;
 pub mod team;
+pub mod timezone;
 pub mod user;
@@ -1,5 +1,3 @@
-pub mod list;
-
 use crate::templates::ToHtml;
 use heck::SnakeCase;
 
@@ -115,6 +113,31 @@ impl PermissionSet {
             delete: false,
         }
     }
+    /// Keeps browsing and reading only, as for the entries of archived teams
+    pub fn restrict_to_read(&mut self) {
+        self.edit = false;
+        self.add = false;
+        self.delete = false;
+    }
+    /// Turns on the permission named by an `access_type` column value
+    pub fn grant(&mut self, access_type: &str) {
+        match access_type {
+            "browse" => self.browse = true,
+            "read" => self.read = true,
+            "edit" => self.edit = true,
+            "add" => self.add = true,
+            "delete" => self.delete = true,
+            _ => (),
+        }
+    }
+    /// The permission types which are not granted by this set
+    pub fn missing(&self) -> Vec<Permission> {
+        let granted = self.as_vec();
+        Permission::all()
+            .into_iter()
+            .filter(|perm| !granted.contains(perm))
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -125,6 +148,27 @@ pub enum Permission {
     Add,
     Delete,
 }
+impl Permission {
+    pub fn all() -> Vec<Permission> {
+        vec![
+            Permission::Browse,
+            Permission::Read,
+            Permission::Edit,
+            Permission::Add,
+            Permission::Delete,
+        ]
+    }
+    /// The `access_type` column value of this permission
+    pub fn access_type(&self) -> &'static str {
+        match self {
+            Permission::Browse => "browse",
+            Permission::Read => "read",
+            Permission::Edit => "edit",
+            Permission::Add => "add",
+            Permission::Delete => "delete",
+        }
+    }
+}
 
 pub fn default_menu() -> Vec<Link> {
     vec![
@@ -169,12 +213,145 @@ pub struct ListContext {
     pub head: String,
     pub title: String,
     pub search: bool,
+    /// Ordering of a list sorted on the server, the headers of its sortable columns become links
+    pub sort: Option<Sort>,
+}
+impl ListContext {
+    /// The link ordering the list by the column, the direction flips on the current column
+    pub fn sort_url(&self, title: &str, pagination: &Option<Pagination>) -> Option<String> {
+        let sort = self.sort.as_ref()?;
+        let key = sort.key_of(title)?;
+        let desc = sort.key == key && !sort.desc;
+        Some(pagination.as_ref()?.sort_url(key, desc))
+    }
+    pub fn sort_icon(&self, title: &str) -> &'static str {
+        match &self.sort {
+            Some(sort) => sort.icon(title),
+            None => "",
+        }
+    }
+}
+
+/// The column a list is ordered by, `columns` pairs the titles of the cells with their sort keys
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Sort {
+    pub key: String,
+    pub desc: bool,
+    pub columns: Vec<(String, String)>,
+}
+impl Sort {
+    pub fn key_of(&self, title: &str) -> Option<&str> {
+        self.columns
+            .iter()
+            .find(|(col, _)| col == title)
+            .map(|(_, key)| key.as_str())
+    }
+    pub fn icon(&self, title: &str) -> &'static str {
+        match self.key_of(title) {
+            Some(key) if key == self.key && self.desc => "fa-sort-down",
+            Some(key) if key == self.key => "fa-sort-up",
+            Some(_) => "fa-sort",
+            None => "",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Row {
     pub cells: Vec<Cell>,
     pub links: Vec<Link>,
+    /// Permissions on the entity of this row, when they differ from the ones of the list
+    pub permission: Option<PermissionSet>,
+    /// What the Delete link removes, the link then posts after a confirmation modal
+    pub confirm: Option<String>,
+}
+impl Row {
+    pub fn allows(&self, perm: &PermissionSet, link: &Link) -> bool {
+        self.permission
+            .as_ref()
+            .unwrap_or(perm)
+            .as_vec()
+            .contains(&link.clearance)
+    }
+    /// Whether the link is to be confirmed in a modal instead of being followed
+    pub fn confirms(&self, link: &Link) -> bool {
+        self.confirm.is_some() && link.clearance == Permission::Delete
+    }
+}
+
+/// Server side paging of a list, `query` is the search term the list was filtered with,
+/// `params` are the other filters and the ordering, kept in the links of the pages
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Pagination {
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub query: String,
+    pub params: Vec<(String, String)>,
+}
+impl Pagination {
+    pub fn new(page: Option<i64>, per_page: i64, total: i64, query: &str) -> Self {
+        let mut pgn = Pagination {
+            page: 1,
+            per_page,
+            total,
+            query: query.to_owned(),
+            params: Vec::new(),
+        };
+        pgn.page = page.unwrap_or(1).max(1).min(pgn.pages());
+        pgn
+    }
+    /// Keeps the given query parameters in the page and sort links, empty values are left out
+    pub fn with_params(mut self, params: Vec<(String, String)>) -> Self {
+        self.params = params.into_iter().filter(|(_, v)| !v.is_empty()).collect();
+        self
+    }
+    pub fn pages(&self) -> i64 {
+        ((self.total + self.per_page - 1) / self.per_page).max(1)
+    }
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+    pub fn url(&self, page: i64) -> String {
+        let mut ser = url::form_urlencoded::Serializer::new(String::new());
+        if !self.query.is_empty() {
+            ser.append_pair("q", &self.query);
+        }
+        for (key, value) in &self.params {
+            ser.append_pair(key, value);
+        }
+        ser.append_pair("page", &page.to_string());
+        format!("?{}", ser.finish())
+    }
+    /// The first page of the list ordered by `key`
+    pub fn sort_url(&self, key: &str, desc: bool) -> String {
+        let mut ser = url::form_urlencoded::Serializer::new(String::new());
+        if !self.query.is_empty() {
+            ser.append_pair("q", &self.query);
+        }
+        for (name, value) in &self.params {
+            if name != "sort" && name != "dir" {
+                ser.append_pair(name, value);
+            }
+        }
+        ser.append_pair("sort", key);
+        ser.append_pair("dir", if desc { "desc" } else { "asc" });
+        format!("?{}", ser.finish())
+    }
+    pub fn prev(&self) -> Option<String> {
+        if self.page > 1 {
+            Some(self.url(self.page - 1))
+        } else {
+            None
+        }
+    }
+    pub fn next(&self) -> Option<String> {
+        if self.page < self.pages() {
+            Some(self.url(self.page + 1))
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -214,4 +391,6 @@ pub struct EditableField {
     pub value: String,
     pub links: Vec<Link>,
     pub required: bool,
+    /// Why the submitted value was refused, shown next to the field
+    pub error: Option<String>,
 }
@@ -0,0 +1,405 @@
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use chrono::{DateTime, Utc};
+use diesel::dsl::max;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use futures::future::Future;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::db::{AppState, DbExecutorError, SQuery};
+use crate::modules::user::UserMeta;
+use crate::schema::{todo_items, todos};
+use crate::utils::{write_conn, WriteConn};
+
+use super::data::Project;
+use super::history::{self, EventKind};
+use super::live::{self, TodoEvent};
+use super::order::GAP;
+use super::todo::Todo;
+
+const MAX_TITLE_LEN: usize = 200;
+
+/// A step of the checklist of a todo
+#[derive(Queryable, Identifiable, Associations, Serialize, Deserialize, Debug, Clone)]
+#[belongs_to(Todo)]
+#[table_name = "todo_items"]
+pub struct TodoItem {
+    pub id: i64,
+    pub todo_id: i64,
+    pub title: String,
+    pub completed: bool,
+    pub position: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// How the checklist of a todo rules its completion, stored in `todos.checklist_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChecklistMode {
+    /// The todo is completed with its last item and reopened with any of them
+    Derive,
+    /// The todo is completed by hand, but not while any of its items is open
+    Gate,
+}
+impl ChecklistMode {
+    pub fn all() -> Vec<ChecklistMode> {
+        vec![ChecklistMode::Derive, ChecklistMode::Gate]
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChecklistMode::Derive => "derive",
+            ChecklistMode::Gate => "gate",
+        }
+    }
+    pub fn parse(name: &str) -> Option<ChecklistMode> {
+        ChecklistMode::all()
+            .into_iter()
+            .find(|mode| mode.as_str() == name)
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            ChecklistMode::Derive => "Completed with the last item",
+            ChecklistMode::Gate => "Completed by hand once every item is done",
+        }
+    }
+}
+impl Default for ChecklistMode {
+    fn default() -> Self {
+        ChecklistMode::Derive
+    }
+}
+
+/// The number of completed items, for the n/m counters
+pub fn done(items: &[TodoItem]) -> usize {
+    items.iter().filter(|item| item.completed).count()
+}
+
+/// The checklists of the todos, in order, todos without items are left out
+pub fn for_todos(
+    req: &HttpRequest<AppState>,
+    todo_ids: Vec<i64>,
+) -> Result<HashMap<i64, Vec<TodoItem>>, DbExecutorError> {
+    let query = todo_items::table
+        .filter(todo_items::todo_id.eq_any(todo_ids))
+        .order((todo_items::position.asc(), todo_items::id.asc()));
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<TodoItem>,
+    };
+    let mut res: HashMap<i64, Vec<TodoItem>> = HashMap::new();
+    for item in req.state().rdb.send(select).wait()?? {
+        res.entry(item.todo_id).or_insert_with(Vec::new).push(item);
+    }
+    Ok(res)
+}
+
+/// The checklist of one todo, in order
+pub fn of_todo(
+    req: &HttpRequest<AppState>,
+    todo_id: i64,
+) -> Result<Vec<TodoItem>, DbExecutorError> {
+    Ok(for_todos(req, vec![todo_id])?
+        .remove(&todo_id)
+        .unwrap_or_default())
+}
+
+/// Whether a todo with these items, completed or not, can be set to `value` by hand
+fn allows(mode: ChecklistMode, items: &[bool], value: bool) -> bool {
+    if items.is_empty() {
+        return true;
+    }
+    match mode {
+        ChecklistMode::Derive => false,
+        ChecklistMode::Gate => !value || items.iter().all(|done| *done),
+    }
+}
+
+/// The completion a todo is changed to after its items changed, `None` when it stays as is
+fn following(mode: ChecklistMode, items: &[bool], completed: bool) -> Option<bool> {
+    let all_done = items.iter().all(|done| *done);
+    let value = match mode {
+        ChecklistMode::Derive if !items.is_empty() => all_done,
+        ChecklistMode::Gate if completed && !all_done => false,
+        _ => return None,
+    };
+    if value == completed {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Whether the todo can be set to `value` by hand. Derived todos follow their items and gated
+/// ones are not completed while an item is open.
+pub fn may_complete(conn: &PgConnection, todo: &Todo, value: bool) -> QueryResult<bool> {
+    let items: Vec<bool> = todo_items::table
+        .filter(todo_items::todo_id.eq(todo.id))
+        .select(todo_items::completed)
+        .load(conn)?;
+    Ok(allows(todo.checklist_mode(), &items, value))
+}
+
+/// Brings the completion of the todo in line with its items after they changed, call it in
+/// the transaction of the change. The todo is returned when its completion changed.
+pub fn sync_completion(
+    conn: &PgConnection,
+    todo_id: i64,
+    usr_id: Option<i64>,
+) -> QueryResult<Option<Todo>> {
+    let todo: Todo = todos::table.find(todo_id).first(conn)?;
+    let items: Vec<bool> = todo_items::table
+        .filter(todo_items::todo_id.eq(todo_id))
+        .select(todo_items::completed)
+        .load(conn)?;
+    let value = match following(todo.checklist_mode(), &items, todo.completed) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let updated: Todo = diesel::update(todos::table.find(todo_id))
+        .set((
+            todos::completed.eq(value),
+            todos::completed_at.eq(if value { Some(Utc::now()) } else { None }),
+            todos::completed_by.eq(if value { usr_id } else { None }),
+        ))
+        .get_result(conn)?;
+    history::record(conn, &[todo_id], EventKind::of_completion(value), usr_id)?;
+    Ok(Some(updated))
+}
+
+/// The project and the todo from the path, when the user can edit the todos of the team
+fn editable_todo(req: &HttpRequest<AppState>) -> Result<Option<(Project, Todo)>, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(None),
+    };
+    let aid = match req.match_info().get("aid").map(str::parse::<i64>) {
+        Some(Ok(aid)) => aid,
+        _ => return Ok(None),
+    };
+    if let Some(org_id) = crate::modules::team::writable(req)? {
+        if !crate::modules::team::permission(req, org_id).edit {
+            return Ok(None);
+        }
+        if let Ok(project) = Project::load(req, org_id, pid) {
+            if let Ok(todo) = Todo::load(req, project.uuid, aid) {
+                return Ok(Some((project, todo)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn item_id(req: &HttpRequest<AppState>) -> Option<i64> {
+    req.match_info()
+        .get("iid")
+        .and_then(|iid| iid.parse::<i64>().ok())
+}
+
+/// Runs the change of the checklist and the completion of the todo in one transaction and
+/// tells the open todo lists about both
+fn change<F>(
+    req: &HttpRequest<AppState>,
+    conn: &WriteConn,
+    project: &Project,
+    todo: &Todo,
+    event: TodoEvent,
+    f: F,
+) -> QueryResult<usize>
+where
+    F: Fn(&PgConnection) -> QueryResult<usize>,
+{
+    let usr_id = UserMeta::current(req).ok().map(|usr_meta| usr_meta.user_id);
+    let (changed, synced) = conn.transaction::<_, diesel::result::Error, _>(|| {
+        let changed = f(conn)?;
+        let synced = if changed > 0 {
+            sync_completion(conn, todo.id, usr_id)?
+        } else {
+            None
+        };
+        Ok((changed, synced))
+    })?;
+    if changed > 0 {
+        live::publish(event);
+    }
+    if let Some(parent) = synced {
+        live::publish(TodoEvent::toggled(&parent));
+    }
+    debug!(
+        "Checklist of todo {} of project {} changed: {}",
+        todo.id, project.projectid, changed
+    );
+    Ok(changed)
+}
+
+fn edit_page(project: &Project, todo: &Todo) -> HttpResponse {
+    HttpResponse::Found()
+        .header(
+            "location",
+            format!("/project/{}/todo/{}", project.projectid, todo.id),
+        )
+        .finish()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ItemParams {
+    title: String,
+}
+/// Adds an item at the end of the checklist
+pub fn add((req, form): (HttpRequest<AppState>, Form<ItemParams>)) -> Result<HttpResponse, Error> {
+    let (project, todo) = match editable_todo(&req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let item_title = form.title.trim();
+    if item_title.is_empty() || item_title.chars().count() > MAX_TITLE_LEN {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+    let event = TodoEvent::checklist_changed(project.uuid, todo.id);
+    let conn = write_conn(&req)?;
+    let res = change(&req, &conn, &project, &todo, event, |conn| {
+        let last: Option<i64> = todo_items::table
+            .filter(todo_items::todo_id.eq(todo.id))
+            .select(max(todo_items::position))
+            .first(conn)?;
+        diesel::insert_into(todo_items::table)
+            .values((
+                todo_items::todo_id.eq(todo.id),
+                todo_items::title.eq(item_title),
+                todo_items::position.eq(last.unwrap_or(0) + GAP),
+            ))
+            .execute(conn)
+    });
+    match res {
+        Ok(_) => Ok(edit_page(&project, &todo)),
+        Err(e) => {
+            error!("Adding an item to todo {} failed: {:?}", todo.id, e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ItemToggleParams {
+    value: bool,
+}
+/// Completes or reopens an item, the todo follows it as its checklist mode says
+pub fn toggle(
+    (req, form): (HttpRequest<AppState>, Form<ItemToggleParams>),
+) -> Result<HttpResponse, Error> {
+    let (project, todo) = match editable_todo(&req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let iid = match item_id(&req) {
+        Some(iid) => iid,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let value = form.value;
+    let event = TodoEvent::item_checked(project.uuid, todo.id, iid, value);
+    let conn = write_conn(&req)?;
+    let res = change(&req, &conn, &project, &todo, event, |conn| {
+        diesel::update(
+            todo_items::table
+                .filter(todo_items::id.eq(iid))
+                .filter(todo_items::todo_id.eq(todo.id))
+                .filter(todo_items::completed.ne(value)),
+        )
+        .set(todo_items::completed.eq(value))
+        .execute(conn)
+    });
+    match res {
+        Ok(_) => Ok(HttpResponse::Ok().finish()),
+        Err(e) => {
+            error!("Toggling item {} of todo {} failed: {:?}", iid, todo.id, e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+/// Removes an item from the checklist
+pub fn delete(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let (project, todo) = match editable_todo(req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let iid = match item_id(req) {
+        Some(iid) => iid,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let event = TodoEvent::checklist_changed(project.uuid, todo.id);
+    let conn = write_conn(req)?;
+    let res = change(req, &conn, &project, &todo, event, |conn| {
+        diesel::delete(
+            todo_items::table
+                .filter(todo_items::id.eq(iid))
+                .filter(todo_items::todo_id.eq(todo.id)),
+        )
+        .execute(conn)
+    });
+    match res {
+        Ok(_) => Ok(edit_page(&project, &todo)),
+        Err(e) => {
+            error!("Deleting item {} of todo {} failed: {:?}", iid, todo.id, e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{allows, following, ChecklistMode};
+
+    #[test]
+    fn completing_by_hand() {
+        let cases: &[(ChecklistMode, &[bool], bool, bool)] = &[
+            // without items the todo is completed by hand in either mode
+            (ChecklistMode::Derive, &[], true, true),
+            (ChecklistMode::Gate, &[], true, true),
+            // derived todos only follow their items
+            (ChecklistMode::Derive, &[true, true], true, false),
+            (ChecklistMode::Derive, &[true, false], false, false),
+            // gated ones wait for every item, but can be reopened any time
+            (ChecklistMode::Gate, &[true, false], true, false),
+            (ChecklistMode::Gate, &[true, true], true, true),
+            (ChecklistMode::Gate, &[true, false], false, true),
+        ];
+        for (mode, items, value, expected) in cases.iter() {
+            assert_eq!(
+                allows(*mode, items, *value),
+                *expected,
+                "{:?} {:?} {}",
+                mode,
+                items,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn following_the_items() {
+        let cases: &[(ChecklistMode, &[bool], bool, Option<bool>)] = &[
+            (ChecklistMode::Derive, &[], false, None),
+            (ChecklistMode::Derive, &[], true, None),
+            (ChecklistMode::Derive, &[true, true], false, Some(true)),
+            (ChecklistMode::Derive, &[true, true], true, None),
+            (ChecklistMode::Derive, &[true, false], true, Some(false)),
+            (ChecklistMode::Derive, &[false], false, None),
+            // gated todos are reopened with an item, never completed by one
+            (ChecklistMode::Gate, &[true, false], true, Some(false)),
+            (ChecklistMode::Gate, &[true, true], false, None),
+            (ChecklistMode::Gate, &[true, true], true, None),
+            (ChecklistMode::Gate, &[], true, None),
+        ];
+        for (mode, items, completed, expected) in cases.iter() {
+            assert_eq!(
+                following(*mode, items, *completed),
+                *expected,
+                "{:?} {:?} {}",
+                mode,
+                items,
+                completed
+            );
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use ::uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use futures::future::Future;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use crate::db::{AppState, DbExecutorError, SQuery};
+use crate::modules::access::allowed;
+use crate::modules::navigation::PermissionSet;
+use crate::modules::timezone;
+use crate::modules::user::UserMeta;
+use crate::schema::{comments, todo_events, todos, user_meta};
+use crate::utils::write_conn;
+
+use super::data::Project;
+use super::history::TodoStateEvent;
+use super::todo::Todo;
+
+/// How long authors can change or take back their comments after posting them
+pub const EDIT_WINDOW_MINUTES: i64 = 15;
+/// The number of comments and of state changes shown in a feed, each
+const FEED_LEN: i64 = 100;
+const MAX_BODY_LEN: usize = 10000;
+
+/// A comment on a project, or on one of its todos when `todo_id` is set. The body is Markdown.
+#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug, Clone)]
+#[table_name = "comments"]
+pub struct Comment {
+    pub id: i64,
+    pub project_id: Uuid,
+    pub todo_id: Option<i64>,
+    pub user_id: i64,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+}
+impl Comment {
+    /// Authors can change their comments during the edit window
+    pub fn editable_by(&self, usr_id: i64, now: &DateTime<Utc>) -> bool {
+        self.user_id == usr_id && *now - self.created_at < Duration::minutes(EDIT_WINDOW_MINUTES)
+    }
+}
+
+/// A comment as shown in a feed
+#[derive(Debug, Clone, Serialize)]
+pub struct CommentView {
+    pub id: i64,
+    pub body: String,
+    pub html: String,
+    pub edited: bool,
+    pub editable: bool,
+    pub deletable: bool,
+}
+
+/// A comment or a change of the state of a todo, as shown in the activity feed
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityEntry {
+    pub at: String,
+    pub user: String,
+    /// The id and title of the todo it is about, `None` for comments on the project
+    pub todo: Option<(i64, String)>,
+    /// The `EventKind` of state changes
+    pub event: Option<String>,
+    pub comment: Option<CommentView>,
+}
+
+/// The latest comments and state changes of the project, or of one of its todos, the latest
+/// first. `usr_id` is the reader, `perm` their permissions on the project.
+pub fn activity(
+    req: &HttpRequest<AppState>,
+    project: &Project,
+    todo_id: Option<i64>,
+    usr_id: i64,
+    perm: &PermissionSet,
+    tz: Tz,
+) -> Result<Vec<ActivityEntry>, DbExecutorError> {
+    let mut query = comments::table
+        .filter(comments::project_id.eq(project.uuid))
+        .order((comments::created_at.desc(), comments::id.desc()))
+        .limit(FEED_LEN)
+        .into_boxed();
+    if let Some(aid) = todo_id {
+        query = query.filter(comments::todo_id.eq(aid));
+    }
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<Comment>,
+    };
+    let notes = req.state().rdb.send(select).wait()??;
+
+    let mut query = todo_events::table
+        .inner_join(todos::table)
+        .filter(todos::project_id.eq(project.uuid))
+        .order((todo_events::created_at.desc(), todo_events::id.desc()))
+        .limit(FEED_LEN)
+        .select(todo_events::all_columns)
+        .into_boxed();
+    if let Some(aid) = todo_id {
+        query = query.filter(todo_events::todo_id.eq(aid));
+    }
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<TodoStateEvent>,
+    };
+    let events = req.state().rdb.send(select).wait()??;
+
+    let user_ids: HashSet<i64> = notes
+        .iter()
+        .map(|note| note.user_id)
+        .chain(events.iter().filter_map(|ev| ev.user_id))
+        .collect();
+    let query = user_meta::table
+        .filter(user_meta::user_id.eq_any(user_ids.into_iter().collect::<Vec<i64>>()))
+        .select((user_meta::user_id, user_meta::display));
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<(i64, String)>,
+    };
+    let names: HashMap<i64, String> = req.state().rdb.send(select).wait()??.into_iter().collect();
+
+    let todo_ids: HashSet<i64> = notes
+        .iter()
+        .filter_map(|note| note.todo_id)
+        .chain(events.iter().map(|ev| ev.todo_id))
+        .collect();
+    let query = todos::table
+        .filter(todos::id.eq_any(todo_ids.into_iter().collect::<Vec<i64>>()))
+        .select((todos::id, todos::title));
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<(i64, String)>,
+    };
+    let titles: HashMap<i64, String> = req.state().rdb.send(select).wait()??.into_iter().collect();
+
+    let name = |usr_id: Option<i64>| {
+        usr_id
+            .and_then(|usr_id| names.get(&usr_id).cloned())
+            .unwrap_or_default()
+    };
+    let title = |aid: i64| (aid, titles.get(&aid).cloned().unwrap_or_default());
+    let now = Utc::now();
+    let mut feed: Vec<(DateTime<Utc>, ActivityEntry)> = Vec::new();
+    for note in notes {
+        let editable = note.editable_by(usr_id, &now);
+        feed.push((
+            note.created_at,
+            ActivityEntry {
+                at: timezone::format_datetime(&note.created_at, tz),
+                user: name(Some(note.user_id)),
+                todo: note.todo_id.map(title),
+                event: None,
+                comment: Some(CommentView {
+                    id: note.id,
+                    html: crate::utils::markdown_html(&note.body),
+                    body: note.body,
+                    edited: note.edited_at.is_some(),
+                    editable,
+                    deletable: editable || perm.delete,
+                }),
+            },
+        ));
+    }
+    for ev in events {
+        feed.push((
+            ev.created_at,
+            ActivityEntry {
+                at: timezone::format_datetime(&ev.created_at, tz),
+                user: name(ev.user_id),
+                todo: Some(title(ev.todo_id)),
+                event: Some(ev.event),
+                comment: None,
+            },
+        ));
+    }
+    feed.sort_by(|a, b| b.0.cmp(&a.0));
+    feed.truncate(FEED_LEN as usize);
+    Ok(feed.into_iter().map(|(_, entry)| entry).collect())
+}
+
+/// What the user may see of the activity of the project or todo, checked against the access
+/// control of the team of the project, and whether they can comment on it
+pub fn feed(
+    req: &HttpRequest<AppState>,
+    project: &Project,
+    todo_id: Option<i64>,
+    tz: Tz,
+) -> (Vec<ActivityEntry>, bool) {
+    let org = match crate::modules::team::load(req, project.team_id) {
+        Ok(org) => org,
+        Err(_) => return (Vec::new(), false),
+    };
+    let perm = allowed(req, org.access_control_id);
+    let usr_meta = match UserMeta::current(req) {
+        Ok(usr_meta) if perm.read => usr_meta,
+        _ => return (Vec::new(), false),
+    };
+    let entries = activity(req, project, todo_id, usr_meta.user_id, &perm, tz).unwrap_or_default();
+    (entries, perm.add && !org.is_archived())
+}
+
+/// The project from the path with the permissions of the user on it, when they can read it
+/// and its team is not archived
+fn commented_project(
+    req: &HttpRequest<AppState>,
+) -> Result<Option<(Project, PermissionSet)>, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(None),
+    };
+    if let Some(org_id) = crate::modules::team::writable(req)? {
+        if let Ok(org) = crate::modules::team::load(req, org_id) {
+            let perm = allowed(req, org.access_control_id);
+            if perm.read {
+                return Ok(Project::load(req, org.id, pid).ok().map(|p| (p, perm)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn comment_id(req: &HttpRequest<AppState>) -> Option<i64> {
+    req.match_info()
+        .get("cid")
+        .and_then(|cid| cid.parse::<i64>().ok())
+}
+
+/// Back to the page the comment is shown on
+fn back(project: &Project, todo_id: Option<i64>) -> HttpResponse {
+    let location = match todo_id {
+        Some(aid) => format!("/project/{}/todo/{}", project.projectid, aid),
+        None => format!("/project/{}", project.projectid),
+    };
+    HttpResponse::Found().header("location", location).finish()
+}
+
+fn valid_body(body: &str) -> Option<&str> {
+    let body = body.trim();
+    if body.is_empty() || body.chars().count() > MAX_BODY_LEN {
+        None
+    } else {
+        Some(body)
+    }
+}
+
+/// The comment from the path, when it belongs to the project
+fn load(
+    req: &HttpRequest<AppState>,
+    project: &Project,
+    cid: i64,
+) -> Result<Comment, DbExecutorError> {
+    let query = comments::table
+        .filter(comments::id.eq(cid))
+        .filter(comments::project_id.eq(project.uuid));
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<Comment>,
+    };
+    match req.state().rdb.send(select).wait()??.into_iter().next() {
+        Some(note) => Ok(note),
+        None => Err(DbExecutorError::Unknown),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CommentParams {
+    body: String,
+    /// The todo commented on, the project itself when missing
+    todo: Option<i64>,
+}
+/// Posts a comment, anyone who can add todos to the project can comment on it
+pub fn add(
+    (req, form): (HttpRequest<AppState>, Form<CommentParams>),
+) -> Result<HttpResponse, Error> {
+    let (project, perm) = match commented_project(&req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let usr_meta = match UserMeta::current(&req) {
+        Ok(usr_meta) => usr_meta,
+        Err(_) => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    if !perm.add {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let body = match valid_body(&form.body) {
+        Some(body) => body,
+        None => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    if let Some(aid) = form.todo {
+        if Todo::load(&req, project.uuid, aid).is_err() {
+            return Ok(HttpResponse::NotFound().finish());
+        }
+    }
+    let conn = write_conn(&req)?;
+    let res = diesel::insert_into(comments::table)
+        .values((
+            comments::project_id.eq(project.uuid),
+            comments::todo_id.eq(form.todo),
+            comments::user_id.eq(usr_meta.user_id),
+            comments::body.eq(body),
+        ))
+        .execute(&conn);
+    if let Err(e) = res {
+        error!(
+            "Commenting on project {} failed: {:?}",
+            project.projectid, e
+        );
+        return Ok(HttpResponse::InternalServerError().finish());
+    }
+    Ok(back(&project, form.todo))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EditParams {
+    body: String,
+}
+/// Changes a comment, only its author can and only during the edit window
+pub fn edit((req, form): (HttpRequest<AppState>, Form<EditParams>)) -> Result<HttpResponse, Error> {
+    let (project, _) = match commented_project(&req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let note = match comment_id(&req).map(|cid| load(&req, &project, cid)) {
+        Some(Ok(note)) => note,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let now = Utc::now();
+    match UserMeta::current(&req) {
+        Ok(ref usr_meta) if note.editable_by(usr_meta.user_id, &now) => {}
+        _ => return Ok(HttpResponse::Forbidden().finish()),
+    }
+    let body = match valid_body(&form.body) {
+        Some(body) => body,
+        None => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    let conn = write_conn(&req)?;
+    let res = diesel::update(comments::table.find(note.id))
+        .set((comments::body.eq(body), comments::edited_at.eq(Some(now))))
+        .execute(&conn);
+    if let Err(e) = res {
+        error!("Editing comment {} failed: {:?}", note.id, e);
+        return Ok(HttpResponse::InternalServerError().finish());
+    }
+    Ok(back(&project, note.todo_id))
+}
+
+/// Removes a comment, by its author during the edit window or by those who can delete todos
+pub fn delete(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let (project, perm) = match commented_project(req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let note = match comment_id(req).map(|cid| load(req, &project, cid)) {
+        Some(Ok(note)) => note,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let author = UserMeta::current(req)
+        .map(|usr_meta| note.editable_by(usr_meta.user_id, &Utc::now()))
+        .unwrap_or(false);
+    if !author && !perm.delete {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let conn = write_conn(req)?;
+    let res = diesel::delete(comments::table.find(note.id)).execute(&conn);
+    if let Err(e) = res {
+        error!("Deleting comment {} failed: {:?}", note.id, e);
+        return Ok(HttpResponse::InternalServerError().finish());
+    }
+    Ok(back(&project, note.todo_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Comment, EDIT_WINDOW_MINUTES};
+    use ::uuid::Uuid;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn authors_edit_within_the_window() {
+        let posted = Utc.ymd(2019, 4, 4).and_hms(12, 0, 0);
+        let note = Comment {
+            id: 1,
+            project_id: Uuid::nil(),
+            todo_id: None,
+            user_id: 7,
+            body: "Called, no answer".to_string(),
+            created_at: posted,
+            edited_at: None,
+        };
+        let window = Duration::minutes(EDIT_WINDOW_MINUTES);
+        let cases = [
+            (7, posted, true),
+            (7, posted + window - Duration::seconds(1), true),
+            (7, posted + window, false),
+            (7, posted + Duration::days(1), false),
+            // nobody else, not even right away
+            (8, posted, false),
+        ];
+        for (usr_id, now, expected) in cases.iter() {
+            assert_eq!(
+                note.editable_by(*usr_id, now),
+                *expected,
+                "{} at {}",
+                usr_id,
+                now
+            );
+        }
+    }
+}
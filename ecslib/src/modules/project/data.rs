@@ -1,5 +1,9 @@
+use std::collections::HashMap;
+
+use crate::modules::timezone;
 use crate::schema::projects;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 
 #[derive(
     Insertable,
@@ -22,6 +26,8 @@ pub struct Project {
     pub content: String,
     pub start_date: Option<DateTime<Utc>>,
     pub end_date: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub is_template: bool,
 }
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ProjectData {
@@ -35,3 +41,114 @@ pub struct ProjectData {
     #[serde(default)]
     pub ecs_end_date: Option<String>,
 }
+impl ProjectData {
+    /// The start and end dates read in `tz`, or the messages for the form by field name
+    pub fn dates(
+        &self,
+        tz: Tz,
+    ) -> Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>), HashMap<String, String>> {
+        let mut errors = HashMap::new();
+        let mut read = |name: &str, value: &Option<String>| match timezone::parse_input(
+            value.as_ref().map_or("", String::as_str),
+            tz,
+        ) {
+            Ok(date) => date,
+            Err(msg) => {
+                errors.insert(name.to_owned(), msg);
+                None
+            }
+        };
+        let start = read("ecs_start_date", &self.ecs_start_date);
+        let end = read("ecs_end_date", &self.ecs_end_date);
+        if let (Some(start), Some(end)) = (start, end) {
+            if end < start {
+                errors.insert(
+                    "ecs_end_date".to_owned(),
+                    "The end date is before the start date".to_owned(),
+                );
+            }
+        }
+        if errors.is_empty() {
+            Ok((start, end))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Where a project stands. It is decided by the `project_statuses` view, so the project page,
+/// the list and the team dashboard all follow the same rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectStatus {
+    Upcoming,
+    Active,
+    Overdue,
+    Finished,
+}
+impl ProjectStatus {
+    pub fn all() -> Vec<ProjectStatus> {
+        vec![
+            ProjectStatus::Upcoming,
+            ProjectStatus::Active,
+            ProjectStatus::Overdue,
+            ProjectStatus::Finished,
+        ]
+    }
+    pub fn parse(status: &str) -> Option<ProjectStatus> {
+        ProjectStatus::all()
+            .into_iter()
+            .find(|st| st.as_str() == status)
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProjectStatus::Upcoming => "upcoming",
+            ProjectStatus::Active => "active",
+            ProjectStatus::Overdue => "overdue",
+            ProjectStatus::Finished => "finished",
+        }
+    }
+    /// The bootstrap badge the status is shown with
+    pub fn badge(self) -> &'static str {
+        match self {
+            ProjectStatus::Upcoming => "badge-info",
+            ProjectStatus::Active => "badge-primary",
+            ProjectStatus::Overdue => "badge-danger",
+            ProjectStatus::Finished => "badge-success",
+        }
+    }
+}
+
+/// How many of the todos of a project are done
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TodoSummary {
+    pub total: i64,
+    pub completed: i64,
+}
+impl TodoSummary {
+    pub fn open(&self) -> i64 {
+        self.total - self.completed
+    }
+    /// Completion in whole percents, 0 without todos
+    pub fn percent(&self) -> i64 {
+        if self.total == 0 {
+            0
+        } else {
+            self.completed * 100 / self.total
+        }
+    }
+}
+
+impl Project {
+    /// The content rendered from Markdown, with everything but safe markup removed
+    pub fn content_html(&self) -> String {
+        crate::utils::markdown_html(&self.content)
+    }
+    /// The days of the start and end in `tz`
+    pub fn date_range(&self, tz: Tz) -> String {
+        let fmt = |date: &Option<DateTime<Utc>>| match date {
+            Some(date) => timezone::format_date(date, tz),
+            None => "?".to_string(),
+        };
+        format!("{} - {}", fmt(&self.start_date), fmt(&self.end_date))
+    }
+}
@@ -0,0 +1,223 @@
+use actix_web::{Error, HttpRequest, HttpResponse};
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use futures::future::Future;
+use std::marker::PhantomData;
+
+use crate::db::{AppState, SQuery};
+use crate::modules::access::allowed;
+use crate::modules::meta::default_meta;
+use crate::modules::team::data::Team;
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::render::Failure;
+use crate::schema::{projects, todos};
+use crate::utils::{http_ok, write_conn};
+
+use super::data::Project;
+use super::Change;
+
+/// Days a deleted project is kept in the trash, unless `TRASH_RETENTION_DAYS` says otherwise
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+pub fn retention_days() -> i64 {
+    std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+/// A project in the trash, with the time it is going to be purged
+#[derive(Debug, Clone)]
+pub struct Trashed {
+    pub project: Project,
+    pub deleted_at: DateTime<Utc>,
+    pub purge_at: DateTime<Utc>,
+}
+impl Trashed {
+    pub fn deleted(&self, tz: Tz) -> String {
+        timezone::format_datetime(&self.deleted_at, tz)
+    }
+    pub fn purged(&self, tz: Tz) -> String {
+        timezone::format_date(&self.purge_at, tz)
+    }
+}
+
+/// The team the projects are deleted from, when the user may delete in it
+fn deleting_team(req: &HttpRequest<AppState>, writable: bool) -> Result<Option<Team>, Error> {
+    let org_id = if writable {
+        crate::modules::team::writable(req)?
+    } else {
+        crate::modules::team::current(req)?
+    };
+    if let Some(org_id) = org_id {
+        if let Ok(org) = crate::modules::team::load(req, org_id) {
+            if Change::Delete.allowed(&allowed(req, org.access_control_id)) {
+                return Ok(Some(org));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn path_id(req: &HttpRequest<AppState>) -> Option<i64> {
+    req.match_info().get("id")?.parse::<i64>().ok()
+}
+
+/// Deletes the projects of the team which have been in the trash longer than the retention period
+pub fn purge(conn: &PgConnection, org_id: i64) -> QueryResult<usize> {
+    let cutoff = Utc::now() - Duration::days(retention_days());
+    conn.transaction(|| {
+        let expired = projects::table
+            .filter(projects::team_id.eq(org_id))
+            .filter(projects::deleted_at.lt(cutoff))
+            .select(projects::uuid);
+        diesel::delete(todos::table.filter(todos::project_id.eq_any(expired))).execute(conn)?;
+        diesel::delete(
+            projects::table
+                .filter(projects::team_id.eq(org_id))
+                .filter(projects::deleted_at.lt(cutoff)),
+        )
+        .execute(conn)
+    })
+}
+
+/// Moves the project with its todos to the trash
+pub fn delete(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let org = match deleting_team(req, true)? {
+        Some(org) => org,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let project = match path_id(req).and_then(|pid| Project::load(req, org.id, pid).ok()) {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let now = Utc::now();
+    let conn = write_conn(req)?;
+    let res = conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::update(
+            todos::table
+                .filter(todos::project_id.eq(project.uuid))
+                .filter(todos::deleted_at.is_null()),
+        )
+        .set(todos::deleted_at.eq(now))
+        .execute(&conn)?;
+        diesel::update(projects::table.filter(projects::uuid.eq(project.uuid)))
+            .set(projects::deleted_at.eq(now))
+            .execute(&conn)
+    });
+    debug!("{:?}", res);
+    let res = purge(&conn, org.id);
+    debug!("{:?}", res);
+    Ok(HttpResponse::Found()
+        .header("location", "/project/list")
+        .finish())
+}
+
+/// Takes the project out of the trash, with the todos which were deleted together with it
+pub fn restore(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let org = match deleting_team(req, true)? {
+        Some(org) => org,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let pid = match path_id(req) {
+        Some(pid) => pid,
+        None => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    let conn = write_conn(req)?;
+    let res = conn.transaction::<_, diesel::result::Error, _>(|| {
+        let project: Project = projects::table
+            .filter(projects::team_id.eq(org.id))
+            .filter(projects::projectid.eq(pid))
+            .filter(projects::deleted_at.is_not_null())
+            .first(&conn)?;
+        diesel::update(
+            todos::table
+                .filter(todos::project_id.eq(project.uuid))
+                .filter(todos::deleted_at.eq(project.deleted_at)),
+        )
+        .set(todos::deleted_at.eq(None::<DateTime<Utc>>))
+        .execute(&conn)?;
+        diesel::update(projects::table.filter(projects::uuid.eq(project.uuid)))
+            .set(projects::deleted_at.eq(None::<DateTime<Utc>>))
+            .execute(&conn)
+    });
+    debug!("{:?}", res);
+    Ok(HttpResponse::Found()
+        .header("location", "/project/trash")
+        .finish())
+}
+
+fn trash_render(
+    data: &[Trashed],
+    retention: i64,
+    tz: Tz,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let cnt = ructe_block_res!(crate::templates::project::trash, data, retention, tz)?;
+    let meta = default_meta("Project Trash");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+/// The deleted projects of the selected team, the expired ones are purged first
+pub fn trash(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let org = match deleting_team(req, false)? {
+        Some(org) => org,
+        None => {
+            if crate::modules::team::current(req)?.is_none() {
+                return Ok(HttpResponse::Found()
+                    .header("location", "/team/select")
+                    .finish());
+            }
+            return Ok(HttpResponse::Forbidden().finish());
+        }
+    };
+    if !org.is_archived() {
+        let conn = write_conn(req)?;
+        let res = purge(&conn, org.id);
+        debug!("{:?}", res);
+    }
+    let query = projects::table
+        .filter(projects::team_id.eq(org.id))
+        .filter(projects::deleted_at.is_not_null())
+        .order(projects::deleted_at.desc());
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<Project>,
+    };
+    let data = match req.state().rdb.send(select).wait() {
+        Ok(Ok(data)) => data,
+        _ => return Ok(HttpResponse::InternalServerError().finish()),
+    };
+    let retention = retention_days();
+    let trashed: Vec<Trashed> = data
+        .into_iter()
+        .filter_map(|project| {
+            let deleted_at = project.deleted_at?;
+            Some(Trashed {
+                project,
+                deleted_at,
+                purge_at: deleted_at + Duration::days(retention),
+            })
+        })
+        .collect();
+    http_ok(trash_render(
+        &trashed,
+        retention,
+        timezone::current(req),
+        &req.impersonating(),
+    ))
+}
@@ -1,30 +1,33 @@
-use actix_web::middleware::session::RequestSession;
 use actix_web::{Error, Form, FromRequest, HttpRequest, HttpResponse, Path};
 
-use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use diesel::prelude::*;
 use futures::future::Future;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 
 use crate::db::{AppState, SQuery, WQuery};
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
 use crate::render::Failure;
 use crate::utils::http_ok;
 
 use crate::schema::projects::dsl::*;
 // use crate::schema::projects;
 use super::data::{Project, ProjectData};
+use super::Change;
 use crate::modules::meta::default_meta;
 
-fn create_fields(data: &[Project]) -> ProjectData {
+fn create_fields(data: &[Project], tz: Tz) -> ProjectData {
     let ecs = data.first().unwrap();
 
     let sdate = if let Some(sdate) = ecs.start_date {
-        Some(sdate.to_rfc3339())
+        Some(sdate.with_timezone(&tz).to_rfc3339())
     } else {
         None
     };
     let edate = if let Some(enddate) = ecs.end_date {
-        Some(enddate.to_rfc3339())
+        Some(enddate.with_timezone(&tz).to_rfc3339())
     } else {
         None
     };
@@ -42,8 +45,11 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
         .unwrap()
         .parse::<i64>()
         .unwrap();
-    if let Some(orgid) = req.session().get::<i64>("org")? {
-        let query = projects.filter(projectid.eq(id)).filter(team_id.eq(orgid));
+    if let Some(orgid) = crate::modules::team::current(req)? {
+        let query = projects
+            .filter(projectid.eq(id))
+            .filter(team_id.eq(orgid))
+            .filter(deleted_at.is_null());
         let select = SQuery {
             select: query,
             phantom: PhantomData::<Project>,
@@ -56,23 +62,27 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
             .wait()
         {
             if let Ok(data) = thing {
-                let fields = create_fields(&data);
-                return http_ok(index_render(fields));
+                let fields = create_fields(&data, timezone::current(req));
+                return http_ok(index_render(fields, &HashMap::new(), &req.impersonating()));
             }
         }
     } else {
-        let org_select = "/org/select".to_owned();
+        let org_select = "/team/select".to_owned();
         return Ok(HttpResponse::Found()
             .header("location", org_select)
             .finish());
     }
     Ok(HttpResponse::Ok().finish())
 }
-fn index_render(ecs: ProjectData) -> Result<String, Failure> {
+fn index_render(
+    ecs: ProjectData,
+    errors: &HashMap<String, String>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let links = crate::menu::default_menu();
 
-    let list = ructe_block_res!(crate::templates::project::edit, &ecs)?;
+    let list = ructe_block_res!(crate::templates::project::edit, &ecs, errors)?;
     let mut meta = default_meta("Project Editor");
     meta.add_local_css("/static/ecs_web_kit/css/chunk-vendors.99b7ff43.css");
     ructe_page_res!(
@@ -80,16 +90,22 @@ fn index_render(ecs: ProjectData) -> Result<String, Failure> {
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
 
-pub fn save((req, form): (HttpRequest<AppState>, Form<ProjectData>)) -> HttpResponse {
+pub fn save(
+    (req, form): (HttpRequest<AppState>, Form<ProjectData>),
+) -> Result<HttpResponse, Error> {
     let ecs = Path::<String>::extract(&req)
         .unwrap()
         .parse::<i64>()
         .unwrap();
-    if let Some(org) = req.session().get::<i64>("org").unwrap() {
+    if let Ok(Some(org)) = crate::modules::team::writable(&req) {
+        if !Change::Edit.allowed(&crate::modules::team::permission(&req, org)) {
+            return Ok(HttpResponse::Forbidden().finish());
+        }
         debug!("Editing {:?}/{:?}", org, ecs);
         let cont = if let Some(cnt) = &form.content {
             cnt.trim().to_owned()
@@ -98,34 +114,20 @@ pub fn save((req, form): (HttpRequest<AppState>, Form<ProjectData>)) -> HttpResp
         };
         let titl = &form.title.trim().to_owned();
 
-        let sdate = if form.ecs_start_date.is_some() {
-            match form
-                .ecs_start_date
-                .clone()
-                .unwrap()
-                .parse::<DateTime<Utc>>()
-            {
-                Ok(dt) => Some(dt),
-                Err(e) => {
-                    info!("Error parsing start date: {:?}", e);
-                    None
-                }
+        let (sdate, edate) = match form.dates(timezone::current(&req)) {
+            Ok(dates) => dates,
+            Err(errors) => {
+                return http_ok(index_render(
+                    form.into_inner(),
+                    &errors,
+                    &req.impersonating(),
+                ))
             }
-        } else {
-            None
-        };
-        let edate = if form.ecs_end_date.is_some() {
-            match form.ecs_end_date.clone().unwrap().parse::<DateTime<Utc>>() {
-                Ok(dt) => Some(dt),
-                Err(e) => {
-                    info!("Error parsing date: {:?}", e);
-                    None
-                }
-            }
-        } else {
-            None
         };
-        let target = projects.filter(projectid.eq(ecs)).filter(team_id.eq(org));
+        let target = projects
+            .filter(projectid.eq(ecs))
+            .filter(team_id.eq(org))
+            .filter(deleted_at.is_null());
         let query = diesel::update(target).set((
             // uuid.eq(form.uuid),
             title.eq(titl.clone()),
@@ -148,5 +150,5 @@ pub fn save((req, form): (HttpRequest<AppState>, Form<ProjectData>)) -> HttpResp
             .unwrap();
         debug!("{:?}", res);
     }
-    HttpResponse::Found().header("location", "../list").finish()
+    Ok(HttpResponse::Found().header("location", "../list").finish())
 }
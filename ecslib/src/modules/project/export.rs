@@ -0,0 +1,292 @@
+use actix_web::{error, Error, HttpRequest, HttpResponse, Query};
+
+use bytes::Bytes;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use futures::future::{self, Future};
+use futures::stream::{self, Stream};
+use serde_json::{json, Value};
+
+use crate::db::{AppState, Conn};
+use crate::modules::access::allowed;
+use crate::schema::todos;
+
+use super::data::Project;
+use super::todo::{Priority, Todo};
+
+/// Todos read from the database at a time while the export is streamed
+const CHUNK_SIZE: i64 = 500;
+
+/// The columns which can be exported, in their default order
+pub const COLUMNS: &[&str] = &[
+    "id",
+    "title",
+    "description",
+    "email",
+    "phone",
+    "completed",
+    "completed_at",
+    "completed_by",
+    "created_at",
+    "due_at",
+    "priority",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+/// Which todos are exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    All,
+    Completed,
+    Remaining,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ExportParams {
+    /// `all` (the default), `completed` or `remaining`
+    filter: Option<String>,
+    /// Comma separated names from `COLUMNS`, all of them when missing
+    columns: Option<String>,
+    /// Starts the CSV with a UTF-8 byte order mark, Excel needs it to read the file as UTF-8
+    bom: Option<String>,
+}
+impl ExportParams {
+    fn filter(&self) -> Option<Filter> {
+        match self.filter.as_ref().map(|f| f.trim()) {
+            None | Some("") | Some("all") => Some(Filter::All),
+            Some("completed") => Some(Filter::Completed),
+            Some("remaining") => Some(Filter::Remaining),
+            Some(_) => None,
+        }
+    }
+    /// The requested columns, `None` if any of them is unknown
+    fn columns(&self) -> Option<Vec<&'static str>> {
+        let names = match &self.columns {
+            Some(names) if !names.trim().is_empty() => names,
+            _ => return Some(COLUMNS.to_vec()),
+        };
+        names
+            .split(',')
+            .map(|name| COLUMNS.iter().find(|col| **col == name.trim()).cloned())
+            .collect()
+    }
+    fn bom(&self) -> bool {
+        match self.bom.as_ref().map(|b| b.trim()) {
+            Some("1") | Some("true") | Some("on") => true,
+            _ => false,
+        }
+    }
+}
+
+fn value(todo: &Todo, column: &str) -> Value {
+    match column {
+        "id" => json!(todo.id),
+        "title" => json!(todo.title),
+        "description" => json!(todo.description),
+        "email" => json!(todo.email),
+        "phone" => json!(todo.phone),
+        "completed" => json!(todo.completed),
+        "completed_at" => json!(todo.completed_at.map(|at| at.to_rfc3339())),
+        "completed_by" => json!(todo.completed_by),
+        "created_at" => json!(todo.created_at.to_rfc3339()),
+        "due_at" => json!(todo.due_at.map(|at| at.to_rfc3339())),
+        "priority" => json!(Priority::of(todo.priority).as_str()),
+        _ => Value::Null,
+    }
+}
+
+fn csv_field(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// Writes the records with the quoting of RFC 4180
+fn csv_bytes(records: Vec<Vec<String>>) -> Result<Bytes, Error> {
+    let mut wtr = ::csv::Writer::from_writer(Vec::new());
+    for record in records {
+        wtr.write_record(&record)
+            .map_err(error::ErrorInternalServerError)?;
+    }
+    let buf = wtr
+        .into_inner()
+        .map_err(|e| error::ErrorInternalServerError(e.to_string()))?;
+    Ok(Bytes::from(buf))
+}
+
+/// The todos of the project after the one with id `after`, in id order
+fn chunk(
+    conn: &PgConnection,
+    project: &Project,
+    filter: Filter,
+    after: i64,
+) -> QueryResult<Vec<Todo>> {
+    let query = todos::table
+        .filter(todos::project_id.eq(project.uuid))
+        .filter(todos::deleted_at.is_null())
+        .filter(todos::id.gt(after))
+        .order(todos::id.asc())
+        .limit(CHUNK_SIZE)
+        .into_boxed();
+    let query = match filter {
+        Filter::All => query,
+        Filter::Completed => query.filter(todos::completed.eq(true)),
+        Filter::Remaining => query.filter(todos::completed.eq(false)),
+    };
+    query.load(conn)
+}
+
+/// The chunk of the export with the given todos, `first` when nothing was written before
+fn encode(format: Format, columns: &[&str], data: &[Todo], first: bool) -> Result<Bytes, Error> {
+    match format {
+        Format::Csv => csv_bytes(
+            data.iter()
+                .map(|todo| {
+                    columns
+                        .iter()
+                        .map(|col| csv_field(value(todo, col)))
+                        .collect()
+                })
+                .collect(),
+        ),
+        Format::Json => {
+            let mut buf = String::new();
+            for (idx, todo) in data.iter().enumerate() {
+                if !(first && idx == 0) {
+                    buf.push(',');
+                }
+                let obj: serde_json::Map<String, Value> = columns
+                    .iter()
+                    .map(|col| (col.to_string(), value(todo, col)))
+                    .collect();
+                buf.push_str(&Value::Object(obj).to_string());
+                buf.push('\n');
+            }
+            Ok(Bytes::from(buf))
+        }
+    }
+}
+
+/// The project from the path, when the caller can read the projects of the selected team
+fn readable_project(req: &HttpRequest<AppState>) -> Result<Result<Project, HttpResponse>, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(Err(HttpResponse::NotFound().finish())),
+    };
+    let org = match crate::modules::team::current(req)? {
+        Some(org_id) => match crate::modules::team::load(req, org_id) {
+            Ok(org) => org,
+            Err(_) => return Ok(Err(HttpResponse::NotFound().finish())),
+        },
+        None => return Ok(Err(HttpResponse::Forbidden().finish())),
+    };
+    if !allowed(req, org.access_control_id).read {
+        return Ok(Err(HttpResponse::Forbidden().finish()));
+    }
+    match Project::load(req, org.id, pid) {
+        Ok(project) => Ok(Ok(project)),
+        Err(_) => Ok(Err(HttpResponse::NotFound().finish())),
+    }
+}
+
+fn export(
+    req: &HttpRequest<AppState>,
+    params: &ExportParams,
+    format: Format,
+) -> Result<HttpResponse, Error> {
+    let project = match readable_project(req)? {
+        Ok(project) => project,
+        Err(res) => return Ok(res),
+    };
+    let (filter, columns) = match (params.filter(), params.columns()) {
+        (Some(filter), Some(columns)) => (filter, columns),
+        _ => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    let conn = match req.state().rdb.send(Conn {}).wait() {
+        Ok(Ok(conn)) => conn,
+        _ => return Ok(HttpResponse::ServiceUnavailable().finish()),
+    };
+
+    let (head, tail, content_type, extension) = match format {
+        Format::Csv => {
+            let header = columns.iter().map(|col| col.to_string()).collect();
+            let mut head = if params.bom() {
+                b"\xEF\xBB\xBF".to_vec()
+            } else {
+                Vec::new()
+            };
+            head.extend_from_slice(&csv_bytes(vec![header])?);
+            (
+                Bytes::from(head),
+                Bytes::new(),
+                "text/csv; charset=utf-8",
+                "csv",
+            )
+        }
+        Format::Json => (
+            Bytes::from("[\n"),
+            Bytes::from("]\n"),
+            "application/json",
+            "json",
+        ),
+    };
+    let filename = format!(
+        "project_{}_todos_{}.{}",
+        project.projectid,
+        match filter {
+            Filter::All => "all",
+            Filter::Completed => "completed",
+            Filter::Remaining => "remaining",
+        },
+        extension
+    );
+
+    // the chunks are read one by one as the client takes them, keyed by the last id sent
+    let body = stream::unfold(Some((conn, 0i64, true)), move |state| {
+        let (conn, after, first) = state?;
+        let res = chunk(&conn, &project, filter, after)
+            .map_err(error::ErrorInternalServerError)
+            .and_then(|data| {
+                let bytes = encode(format, &columns, &data, first)?;
+                let next = match data.last() {
+                    Some(last) if data.len() as i64 == CHUNK_SIZE => Some((conn, last.id, false)),
+                    _ => None,
+                };
+                Ok((bytes, next))
+            });
+        Some(future::result(res))
+    });
+    let body = stream::once(Ok(head))
+        .chain(body)
+        .chain(stream::once(Ok(tail)))
+        .filter(|bytes: &Bytes| !bytes.is_empty());
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header(
+            "content-disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .streaming(body))
+}
+
+/// The todos of the project as CSV
+pub fn csv(
+    (req, params): (HttpRequest<AppState>, Query<ExportParams>),
+) -> Result<HttpResponse, Error> {
+    export(&req, &params, Format::Csv)
+}
+
+/// The todos of the project as a JSON array of objects
+pub fn json(
+    (req, params): (HttpRequest<AppState>, Query<ExportParams>),
+) -> Result<HttpResponse, Error> {
+    export(&req, &params, Format::Json)
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::schema::todo_events;
+
+/// The changes of the state of a todo which are recorded in `todo_events`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventKind {
+    Created,
+    Completed,
+    Reopened,
+    /// Moved to the trash, the todo and its history are kept
+    Deleted,
+}
+impl EventKind {
+    /// The event of setting the completion of a todo to `value`
+    pub fn of_completion(value: bool) -> Self {
+        if value {
+            EventKind::Completed
+        } else {
+            EventKind::Reopened
+        }
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Created => "created",
+            EventKind::Completed => "completed",
+            EventKind::Reopened => "reopened",
+            EventKind::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Queryable, Debug, Clone, Serialize)]
+pub struct TodoStateEvent {
+    pub id: i64,
+    pub todo_id: i64,
+    pub event: String,
+    pub user_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Records a change of the todos, in the transaction of the change itself
+pub fn record(
+    conn: &PgConnection,
+    todo_ids: &[i64],
+    kind: EventKind,
+    usr_id: Option<i64>,
+) -> QueryResult<usize> {
+    let values: Vec<_> = todo_ids
+        .iter()
+        .map(|todo_id| {
+            (
+                todo_events::todo_id.eq(*todo_id),
+                todo_events::event.eq(kind.as_str()),
+                todo_events::user_id.eq(usr_id),
+            )
+        })
+        .collect();
+    diesel::insert_into(todo_events::table)
+        .values(&values)
+        .execute(conn)
+}
@@ -0,0 +1,468 @@
+use actix_web::middleware::identity::RequestIdentity;
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::collections::HashSet;
+
+use crate::db::AppState;
+use crate::modules::access::allowed;
+use crate::modules::meta::default_meta;
+use crate::modules::navigation::Link;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
+use crate::render::Failure;
+use crate::schema::todos;
+use crate::utils::{http_ok, write_conn};
+
+use super::data::Project;
+use super::history::{self, EventKind};
+use super::order;
+
+/// The columns of a list without a header row, in this order
+pub const DEFAULT_COLUMNS: &[&str] = &["name", "phone", "email", "description"];
+
+const MAX_TITLE_LEN: usize = 200;
+
+/// A todo read from the list, `line` is the line of the file it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportRow {
+    pub line: usize,
+    pub title: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// What an import does or did: the rows to insert, the rejected and the skipped ones
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub header: bool,
+    pub rows: Vec<ImportRow>,
+    pub errors: Vec<RowError>,
+    /// Rows already present in the project or earlier in the list, they are not inserted
+    pub duplicates: Vec<ImportRow>,
+    /// Todos inserted, 0 for dry runs and lists with errors
+    pub imported: usize,
+}
+impl ImportReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Which column holds a field, from the header row when the first row is one
+struct Layout {
+    name: usize,
+    phone: Option<usize>,
+    email: Option<usize>,
+    description: Option<usize>,
+}
+impl Layout {
+    fn default_order() -> Self {
+        Layout {
+            name: 0,
+            phone: Some(1),
+            email: Some(2),
+            description: Some(3),
+        }
+    }
+    /// The layout named by the record, `None` unless every field of it is a known column name
+    fn from_header(record: &::csv::StringRecord) -> Option<Self> {
+        let names: Vec<String> = record.iter().map(|f| f.trim().to_lowercase()).collect();
+        let known = |name: &str| match name {
+            "title" => "name",
+            other => other,
+        };
+        if names.is_empty()
+            || !names
+                .iter()
+                .all(|name| DEFAULT_COLUMNS.contains(&known(name)))
+        {
+            return None;
+        }
+        let find = |col: &str| names.iter().position(|name| known(name) == col);
+        Some(Layout {
+            name: find("name")?,
+            phone: find("phone"),
+            email: find("email"),
+            description: find("description"),
+        })
+    }
+}
+
+fn field(record: &::csv::StringRecord, idx: Option<usize>) -> Option<String> {
+    let value = record.get(idx?)?.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_owned())
+    }
+}
+
+fn validate(row: &ImportRow) -> Option<String> {
+    if row.title.is_empty() {
+        return Some("The name is missing".to_owned());
+    }
+    if row.title.chars().count() > MAX_TITLE_LEN {
+        return Some(format!(
+            "The name is longer than {} characters",
+            MAX_TITLE_LEN
+        ));
+    }
+    if let Some(mail) = &row.email {
+        if !validator::validate_email(mail.as_str()) {
+            return Some(format!("{} is not an email address", mail));
+        }
+    }
+    if let Some(ph) = &row.phone {
+        let allowed_char = |c: char| c.is_ascii_digit() || "+-()/. ".contains(c);
+        if !ph.chars().all(allowed_char) || !ph.chars().any(|c| c.is_ascii_digit()) {
+            return Some(format!("{} is not a phone number", ph));
+        }
+    }
+    None
+}
+
+/// Reads the list, with or without a header row, and validates every row of it
+pub fn parse(input: &[u8]) -> ImportReport {
+    let input = if input.starts_with(b"\xEF\xBB\xBF") {
+        &input[3..]
+    } else {
+        input
+    };
+    let mut rdr = ::csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(input);
+    let mut report = ImportReport::default();
+    let mut layout = None;
+    for (idx, record) in rdr.records().enumerate() {
+        // the reader skips empty lines, the record knows where it started
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                report.errors.push(RowError {
+                    line: e.position().map_or(idx + 1, |pos| pos.line() as usize),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map_or(idx + 1, |pos| pos.line() as usize);
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        // only the first row can be a header
+        if layout.is_none() {
+            if let Some(cols) = Layout::from_header(&record) {
+                report.header = true;
+                layout = Some(cols);
+                continue;
+            }
+        }
+        let cols = layout.get_or_insert_with(Layout::default_order);
+        let row = ImportRow {
+            line,
+            title: field(&record, Some(cols.name)).unwrap_or_default(),
+            phone: field(&record, cols.phone),
+            email: field(&record, cols.email),
+            description: field(&record, cols.description),
+        };
+        match validate(&row) {
+            Some(message) => report.errors.push(RowError { line, message }),
+            None => report.rows.push(row),
+        }
+    }
+    report
+}
+
+/// Todos are the same when their names and emails match, regardless of case
+fn duplicate_key(title: &str, email: &Option<String>) -> (String, String) {
+    (
+        title.trim().to_lowercase(),
+        email
+            .as_ref()
+            .map(|m| m.trim().to_lowercase())
+            .unwrap_or_default(),
+    )
+}
+
+/// Imports the list into the project, all the rows or none of them, `actor` is recorded as
+/// the creator of the todos. Nothing is inserted for dry runs or when any of the rows is
+/// invalid, the report tells why.
+pub fn import(
+    conn: &PgConnection,
+    project: &Project,
+    input: &[u8],
+    dry_run: bool,
+    actor: Option<i64>,
+) -> QueryResult<ImportReport> {
+    let mut report = parse(input);
+    conn.transaction(|| {
+        // concurrent imports of the same list would both find the rows new otherwise
+        order::lock(conn, project.uuid)?;
+        let existing: Vec<(String, Option<String>)> = todos::table
+            .filter(todos::project_id.eq(project.uuid))
+            .filter(todos::deleted_at.is_null())
+            .select((todos::title, todos::email))
+            .load(conn)?;
+        let mut seen: HashSet<(String, String)> = existing
+            .iter()
+            .map(|(title, mail)| duplicate_key(title, mail))
+            .collect();
+        let (rows, duplicates): (Vec<ImportRow>, Vec<ImportRow>) = report
+            .rows
+            .drain(..)
+            .partition(|row| seen.insert(duplicate_key(&row.title, &row.email)));
+        report.rows = rows;
+        report.duplicates = duplicates;
+        if dry_run || !report.is_valid() || report.rows.is_empty() {
+            return Ok(());
+        }
+        let positions = order::append_positions(conn, project.uuid, report.rows.len())?;
+        let values: Vec<_> = report
+            .rows
+            .iter()
+            .zip(positions)
+            .map(|(row, pos)| {
+                (
+                    todos::title.eq(&row.title),
+                    todos::phone.eq(&row.phone),
+                    todos::email.eq(&row.email),
+                    todos::description.eq(&row.description),
+                    todos::project_id.eq(project.uuid),
+                    todos::completed.eq(false),
+                    todos::position.eq(pos),
+                )
+            })
+            .collect();
+        let ids: Vec<i64> = diesel::insert_into(todos::table)
+            .values(&values)
+            .returning(todos::id)
+            .get_results(conn)?;
+        history::record(conn, &ids, EventKind::Created, actor)?;
+        report.imported = ids.len();
+        Ok(())
+    })?;
+    Ok(report)
+}
+
+/// The project from the path when todos can be added to it
+fn importing_project(req: &HttpRequest<AppState>) -> Result<Option<Project>, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(None),
+    };
+    if let Some(org_id) = crate::modules::team::writable(req)? {
+        if let Ok(org) = crate::modules::team::load(req, org_id) {
+            if allowed(req, org.access_control_id).add {
+                return Ok(Project::load(req, org.id, pid).ok());
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn index_render(
+    project: &Project,
+    list: &str,
+    report: &Option<ImportReport>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = vec![
+        Link::new("Project", &format!("/project/{}", project.projectid)),
+        Link::new("Todo list", &format!("/project/{}/todo", project.projectid)),
+    ];
+    let mut cnt = ructe_block_res!(crate::templates::project::import, project, list, report)?;
+    cnt.push_str(r#"<script src="/static/import.js" charset="utf-8"></script>"#);
+    let meta = default_meta("Import Todos");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+/// The upload form
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    match importing_project(req)? {
+        Some(project) => http_ok(index_render(&project, "", &None, &req.impersonating())),
+        None => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImportParams {
+    list: String,
+    /// `import` inserts the todos, anything else only previews them
+    action: Option<String>,
+}
+/// Previews the list, or imports it when asked to and every row is valid
+pub fn save(
+    (req, form): (HttpRequest<AppState>, Form<ImportParams>),
+) -> Result<HttpResponse, Error> {
+    let project = match importing_project(&req)? {
+        Some(project) => project,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let dry_run = form
+        .action
+        .as_ref()
+        .map_or(true, |action| action != "import");
+    let actor = UserMeta::current(&req)
+        .ok()
+        .map(|usr_meta| usr_meta.user_id);
+    let conn = write_conn(&req)?;
+    match import(&conn, &project, form.list.as_bytes(), dry_run, actor) {
+        Ok(ref report) if report.imported > 0 => {
+            info!(
+                "{} todos imported into project {} by {}",
+                report.imported,
+                project.projectid,
+                req.identity().unwrap_or_default()
+            );
+            Ok(HttpResponse::Found()
+                .header("location", format!("/project/{}/todo", project.projectid))
+                .finish())
+        }
+        Ok(report) => http_ok(index_render(
+            &project,
+            &form.list,
+            &Some(report),
+            &req.impersonating(),
+        )),
+        Err(e) => {
+            error!(
+                "Importing into project {} failed: {:?}",
+                project.projectid, e
+            );
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, validate, ImportRow, Layout};
+    use csv::StringRecord;
+
+    fn row(title: &str, phone: Option<&str>, email: Option<&str>) -> ImportRow {
+        ImportRow {
+            line: 1,
+            title: title.to_string(),
+            phone: phone.map(str::to_string),
+            email: email.map(str::to_string),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn header_layouts() {
+        let cases: &[(
+            &[&str],
+            Option<(usize, Option<usize>, Option<usize>, Option<usize>)>,
+        )] = &[
+            (
+                &["name", "phone", "email", "description"],
+                Some((0, Some(1), Some(2), Some(3))),
+            ),
+            (&[" Email ", "TITLE"], Some((1, None, Some(0), None))),
+            (&["description", "name"], Some((1, None, None, Some(0)))),
+            // every column of a header is a known name
+            (&["name", "address"], None),
+            // and one of them is the name
+            (&["phone", "email"], None),
+            (&["Jane Doe", "+36 1 234 5678", "jane@example.com"], None),
+        ];
+        for (fields, expected) in cases.iter() {
+            let layout = Layout::from_header(&StringRecord::from(fields.to_vec()));
+            let found = layout.map(|l| (l.name, l.phone, l.email, l.description));
+            assert_eq!(found, *expected, "{:?}", fields);
+        }
+    }
+
+    #[test]
+    fn row_validation() {
+        let long = "x".repeat(201);
+        let cases = [
+            (
+                row(
+                    "Jane Doe",
+                    Some("+36 (1) 234-5678"),
+                    Some("jane@example.com"),
+                ),
+                true,
+            ),
+            (row("Jane Doe", None, None), true),
+            (row("", None, None), false),
+            (row(&long, None, None), false),
+            (row("Jane Doe", None, Some("jane.example.com")), false),
+            (row("Jane Doe", None, Some("jane@")), false),
+            (row("Jane Doe", Some("call me"), None), false),
+            (row("Jane Doe", Some("+-()"), None), false),
+        ];
+        for (input, valid) in cases.iter() {
+            assert_eq!(validate(input).is_none(), *valid, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn lists_without_header() {
+        let report = parse(b"Jane Doe,+36 1 234 5678,jane@example.com,First call\nJohn Roe\n");
+        assert!(!report.header);
+        assert!(report.is_valid());
+        assert_eq!(report.rows.len(), 2);
+        let jane = &report.rows[0];
+        assert_eq!(jane.line, 1);
+        assert_eq!(jane.title, "Jane Doe");
+        assert_eq!(jane.phone.as_ref().unwrap(), "+36 1 234 5678");
+        assert_eq!(jane.email.as_ref().unwrap(), "jane@example.com");
+        assert_eq!(jane.description.as_ref().unwrap(), "First call");
+        let john = &report.rows[1];
+        assert_eq!(
+            (john.line, john.phone.clone(), john.email.clone()),
+            (2, None, None)
+        );
+    }
+
+    #[test]
+    fn header_after_bom_and_blank_lines() {
+        let report = parse(b"\xEF\xBB\xBF\n , \nEmail,Name\n\njane@example.com,Jane Doe\n");
+        assert!(report.header);
+        assert!(report.is_valid());
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].line, 5);
+        assert_eq!(report.rows[0].title, "Jane Doe");
+        assert_eq!(report.rows[0].email.as_ref().unwrap(), "jane@example.com");
+    }
+
+    #[test]
+    fn only_the_first_row_is_a_header() {
+        let report = parse(b"Jane Doe\nname\n");
+        assert!(!report.header);
+        let titles: Vec<&str> = report.rows.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, vec!["Jane Doe", "name"]);
+    }
+
+    #[test]
+    fn bad_rows_are_reported_by_line() {
+        let report =
+            parse(b"name,email\nJane Doe,jane@example.com\nJohn Roe,john@\n,nobody@example.com\n");
+        assert!(!report.is_valid());
+        assert_eq!(report.rows.len(), 1);
+        let lines: Vec<usize> = report.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 4]);
+        assert!(report.errors[0].message.contains("john@"));
+    }
+}
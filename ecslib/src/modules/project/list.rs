@@ -1,20 +1,278 @@
-use actix_web::middleware::session::RequestSession;
-use actix_web::{Error, HttpRequest, HttpResponse};
+use actix_web::{Error, HttpRequest, HttpResponse, Query};
 
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamptz};
 use futures::future::Future;
-use std::marker::PhantomData;
+use std::collections::HashMap;
 
-use crate::db::{AppState, SQuery};
-use crate::modules::navigation::{Cell, CellContent, Link, ListContext, Permission, Row};
+use crate::db::{AppState, Conn};
+use crate::modules::access::allowed;
+use crate::modules::navigation::{
+    Cell, CellContent, Link, ListContext, Pagination, Permission, PermissionSet, Row, Sort,
+};
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
 use crate::render::Failure;
 use crate::utils::http_ok;
 
-use super::data::Project;
+use super::data::ProjectStatus;
 use crate::modules::meta::default_meta;
-use crate::schema::projects::dsl::*;
 
-fn create_list(data: &[Project], org: i64) -> Vec<Row> {
+const PAGE_SIZES: &[i64] = &[10, 20, 50, 100];
+const DEFAULT_PAGE_SIZE: i64 = 20;
+
+/// The projects of a team with their todo counts and status, filtered by
+/// `$2` search pattern, `$3`-`$4` date range and `$5` status, all of them optional.
+/// The status comes from the `project_statuses` view.
+const LISTED_SQL: &str = r#"
+WITH listed AS (
+  SELECT p.projectid, p.title, p.start_date, p.end_date,
+    s.total_todos, s.done_todos, s.status
+  FROM projects p
+  JOIN project_statuses s ON s.project_id = p.uuid
+  WHERE p.team_id = $1 AND p.deleted_at IS NULL AND NOT p.is_template
+    AND ($2::text IS NULL OR p.title ILIKE $2 OR p.content ILIKE $2)
+    AND ($3::timestamptz IS NULL OR p.end_date IS NULL OR p.end_date >= $3)
+    AND ($4::timestamptz IS NULL OR p.start_date IS NULL OR p.start_date < $4)
+)
+SELECT * FROM listed WHERE ($5::text IS NULL OR status = $5)
+"#;
+
+#[derive(QueryableByName, Debug, Clone)]
+struct ListedProject {
+    #[sql_type = "BigInt"]
+    projectid: i64,
+    #[sql_type = "Text"]
+    title: String,
+    #[sql_type = "Nullable<Timestamptz>"]
+    start_date: Option<DateTime<Utc>>,
+    #[sql_type = "Nullable<Timestamptz>"]
+    end_date: Option<DateTime<Utc>>,
+    #[sql_type = "BigInt"]
+    total_todos: i64,
+    #[sql_type = "BigInt"]
+    done_todos: i64,
+    #[sql_type = "Text"]
+    status: String,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+struct Count {
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+/// The columns the list can be ordered by: title of the cell, sort key and the column of `LISTED_SQL`
+const SORT_COLUMNS: &[(&str, &str, &str)] = &[
+    ("Title", "title", "title"),
+    ("Start Date", "start", "start_date"),
+    ("End Date", "end", "end_date"),
+    ("Status", "status", "status"),
+];
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ListParams {
+    pub q: Option<String>,
+    /// Projects ending on or after this day, `YYYY-MM-DD`
+    pub from: Option<String>,
+    /// Projects starting on or before this day, `YYYY-MM-DD`
+    pub to: Option<String>,
+    pub status: Option<String>,
+    pub sort: Option<String>,
+    pub dir: Option<String>,
+    pub page: Option<i64>,
+    pub size: Option<i64>,
+}
+impl ListParams {
+    fn text(value: &Option<String>) -> String {
+        value
+            .as_ref()
+            .map(|v| v.trim().to_owned())
+            .unwrap_or_default()
+    }
+    pub fn query(&self) -> String {
+        ListParams::text(&self.q)
+    }
+    pub fn from_date(&self) -> String {
+        ListParams::text(&self.from)
+    }
+    pub fn to_date(&self) -> String {
+        ListParams::text(&self.to)
+    }
+    pub fn status(&self) -> Option<ProjectStatus> {
+        ProjectStatus::parse(&ListParams::text(&self.status))
+    }
+    pub fn status_str(&self) -> String {
+        self.status()
+            .map(|st| st.as_str().to_owned())
+            .unwrap_or_default()
+    }
+    pub fn size(&self) -> i64 {
+        match self.size {
+            Some(size) if PAGE_SIZES.contains(&size) => size,
+            _ => DEFAULT_PAGE_SIZE,
+        }
+    }
+    pub fn sizes(&self) -> Vec<i64> {
+        PAGE_SIZES.to_vec()
+    }
+    /// The sort key and the column of `LISTED_SQL`, projects are listed by number by default
+    fn sort_column(&self) -> Option<(&'static str, &'static str)> {
+        let key = ListParams::text(&self.sort);
+        SORT_COLUMNS
+            .iter()
+            .find(|(_, k, _)| *k == key)
+            .map(|(_, k, col)| (*k, *col))
+    }
+    fn desc(&self) -> bool {
+        ListParams::text(&self.dir) == "desc"
+    }
+    fn sort(&self) -> Sort {
+        Sort {
+            key: self
+                .sort_column()
+                .map(|(key, _)| key.to_owned())
+                .unwrap_or_default(),
+            desc: self.desc(),
+            columns: SORT_COLUMNS
+                .iter()
+                .map(|(title, key, _)| ((*title).to_owned(), (*key).to_owned()))
+                .collect(),
+        }
+    }
+    /// The bounds of the date filter, the day given as `to` is included. Unreadable days are
+    /// refused with a message by field name rather than left out of the filter.
+    pub fn dates(
+        &self,
+        tz: Tz,
+    ) -> Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>), HashMap<String, String>> {
+        let mut errors = HashMap::new();
+        let mut read = |name: &str, day: &str, days: i64| match parse_day(day, days, tz) {
+            Ok(date) => date,
+            Err(msg) => {
+                errors.insert(name.to_owned(), msg);
+                None
+            }
+        };
+        let from = read("from", &self.from_date(), 0);
+        let to = read("to", &self.to_date(), 1);
+        if let (Some(from), Some(to)) = (from, to) {
+            if to <= from {
+                errors.insert("to".to_owned(), "The end is before the start".to_owned());
+            }
+        }
+        if errors.is_empty() {
+            Ok((from, to))
+        } else {
+            Err(errors)
+        }
+    }
+    /// The parameters kept in the page and sort links, beside the search term
+    fn link_params(&self) -> Vec<(String, String)> {
+        let mut params = vec![
+            ("from".to_owned(), self.from_date()),
+            ("to".to_owned(), self.to_date()),
+            ("status".to_owned(), self.status_str()),
+            ("size".to_owned(), self.size().to_string()),
+        ];
+        if let Some((key, _)) = self.sort_column() {
+            params.push(("sort".to_owned(), key.to_owned()));
+            params.push((
+                "dir".to_owned(),
+                if self.desc() { "desc" } else { "asc" }.to_owned(),
+            ));
+        }
+        params
+    }
+}
+
+/// The start of the day in `tz`, `days` later than the one given, `Ok(None)` when left empty
+fn parse_day(day: &str, days: i64, tz: Tz) -> Result<Option<DateTime<Utc>>, String> {
+    if day.is_empty() {
+        return Ok(None);
+    }
+    let date = NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map_err(|_| format!("Not a date, use YYYY-MM-DD, got {}", day))?;
+    timezone::parse_input(
+        &(date + Duration::days(days)).format("%Y-%m-%d").to_string(),
+        tz,
+    )
+}
+
+/// Turns a search term into an `ILIKE` pattern, matching it literally anywhere
+fn like_pattern(search: &str) -> Option<String> {
+    if search.is_empty() {
+        return None;
+    }
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    Some(format!("%{}%", escaped))
+}
+
+/// One page of the projects of the team, filtered, ordered and counted by Postgres
+fn load_page(
+    conn: &PgConnection,
+    org_id: i64,
+    params: &ListParams,
+    (from, to): (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+) -> QueryResult<(Vec<ListedProject>, Pagination)> {
+    use diesel::sql_query;
+    let pattern = like_pattern(&params.query());
+    let status = params.status().map(|st| st.as_str().to_owned());
+
+    let counted: Vec<Count> =
+        sql_query(format!("SELECT COUNT(*) AS count FROM ({}) c", LISTED_SQL))
+            .bind::<BigInt, _>(org_id)
+            .bind::<Nullable<Text>, _>(pattern.clone())
+            .bind::<Nullable<Timestamptz>, _>(from)
+            .bind::<Nullable<Timestamptz>, _>(to)
+            .bind::<Nullable<Text>, _>(status.clone())
+            .load(conn)?;
+    let total = counted.first().map(|c| c.count).unwrap_or(0);
+    let pagination = Pagination::new(params.page, params.size(), total, &params.query())
+        .with_params(params.link_params());
+
+    let order = match params.sort_column() {
+        Some((_, col)) => format!(
+            "{} {} NULLS LAST, projectid",
+            col,
+            if params.desc() { "DESC" } else { "ASC" }
+        ),
+        None => "projectid".to_owned(),
+    };
+    let data = sql_query(format!(
+        "{} ORDER BY {} LIMIT $6 OFFSET $7",
+        LISTED_SQL, order
+    ))
+    .bind::<BigInt, _>(org_id)
+    .bind::<Nullable<Text>, _>(pattern)
+    .bind::<Nullable<Timestamptz>, _>(from)
+    .bind::<Nullable<Timestamptz>, _>(to)
+    .bind::<Nullable<Text>, _>(status)
+    .bind::<BigInt, _>(pagination.per_page)
+    .bind::<BigInt, _>(pagination.offset())
+    .load(conn)?;
+    Ok((data, pagination))
+}
+
+fn date_cell(title: &str, date: &Option<DateTime<Utc>>, tz: Tz) -> Cell {
+    let content = match date {
+        Some(date) => timezone::format_date(date, tz),
+        None => String::new(),
+    };
+    Cell {
+        title: title.to_string(),
+        content: CellContent::new(content),
+        is_nullable: true,
+    }
+}
+
+fn create_list(data: &[ListedProject], org: i64, perm: &PermissionSet, tz: Tz) -> Vec<Row> {
     let mut res = Vec::new();
     debug!("Listing org:{}, data:{:?}", org, data);
     for ent in data {
@@ -27,26 +285,26 @@ fn create_list(data: &[Project], org: i64) -> Vec<Row> {
             is_nullable: false,
         };
         cells.push(title_cell);
+        cells.push(date_cell("Start Date", &ent.start_date, tz));
+        cells.push(date_cell("End Date", &ent.end_date, tz));
 
-        if let Some(startdate) = ent.start_date {
-            let start_date_cont = CellContent::new(startdate.to_string());
-            let start_date_cell = Cell {
-                title: "Start Date".to_string(),
-                content: start_date_cont,
-                is_nullable: true,
-            };
-            cells.push(start_date_cell);
-        }
-        if let Some(enddate) = ent.end_date {
-            let end_date_cont = CellContent::new(enddate.to_string());
-            let end_date_cell = Cell {
-                title: "End Date".to_string(),
-                content: end_date_cont,
-                is_nullable: true,
-            };
-            cells.push(end_date_cell);
-        }
+        let mut status_cont = CellContent::new(ent.status.clone());
+        status_cont.detail.0 = format!("{} / {} todos done", ent.done_todos, ent.total_todos);
+        let status_cell = Cell {
+            title: "Status".to_string(),
+            content: status_cont,
+            is_nullable: false,
+        };
+        cells.push(status_cell);
 
+        let view = Link {
+            visual: "View".to_string(),
+            url: format!("/project/{}", ent.projectid),
+            active: false,
+            icon: "fa-eye".to_string(),
+            clearance: Permission::Read,
+            children: None,
+        };
         let ed = Link {
             visual: "Edit".to_string(),
             url: format!("/project/{}/edit", ent.projectid),
@@ -63,61 +321,139 @@ fn create_list(data: &[Project], org: i64) -> Vec<Row> {
             clearance: Permission::Delete,
             children: None,
         };
-        let links = vec![ed, del];
-        let row = Row { cells, links };
+        let links = vec![view, ed, del];
+        let row = Row {
+            cells,
+            links,
+            permission: Some(perm.clone()),
+            confirm: Some(ent.title.clone()),
+        };
         res.push(row);
     }
     res
 }
-pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
-    if let Some(orgid) = req.session().get::<i64>("org")? {
-        let query = projects.filter(team_id.eq(orgid));
-        let select = SQuery {
-            select: query,
-            phantom: PhantomData::<Project>,
-        };
-        if let Ok(thing) = req
-            .state()
-            .rdb
-            .send(select)
-            .map_err(actix_web::Error::from)
-            .wait()
-        {
-            if let Ok(data) = thing {
-                let list = create_list(&data, orgid);
-                return http_ok(index_render(list));
-            }
+
+pub fn index(
+    (req, params): (HttpRequest<AppState>, Query<ListParams>),
+) -> Result<HttpResponse, Error> {
+    let org = match crate::modules::team::current(&req)? {
+        Some(orgid) => match crate::modules::team::load(&req, orgid) {
+            Ok(org) => org,
+            Err(_) => return Ok(HttpResponse::NotFound().finish()),
+        },
+        None => {
+            let org_select = "/team/select".to_owned();
+            return Ok(HttpResponse::Found()
+                .header("location", org_select)
+                .finish());
+        }
+    };
+    // projects are governed by the access control entry of their team
+    let perm = allowed(&req, org.access_control_id);
+    let conn = match req.state().rdb.send(Conn {}).wait() {
+        Ok(Ok(conn)) => conn,
+        _ => return Ok(HttpResponse::ServiceUnavailable().finish()),
+    };
+    let tz = timezone::current(&req);
+    let dates = match params.dates(tz) {
+        Ok(dates) => dates,
+        Err(errors) => {
+            return http_ok(index_render(
+                Vec::new(),
+                &params,
+                &errors,
+                None,
+                &perm,
+                &req.impersonating(),
+            ))
+        }
+    };
+    match load_page(&conn, org.id, &params, dates) {
+        Ok((data, pagination)) => {
+            let list = create_list(&data, org.id, &perm, tz);
+            http_ok(index_render(
+                list,
+                &params,
+                &HashMap::new(),
+                Some(pagination),
+                &perm,
+                &req.impersonating(),
+            ))
+        }
+        Err(e) => {
+            error!("Listing projects of team {} failed: {:?}", org.id, e);
+            Ok(HttpResponse::InternalServerError().finish())
         }
-    } else {
-        let org_select = "/org/select".to_owned();
-        return Ok(HttpResponse::Found()
-            .header("location", org_select)
-            .finish());
     }
-    Ok(HttpResponse::Ok().finish())
 }
-fn index_render(list: Vec<Row>) -> Result<String, Failure> {
+fn index_render(
+    list: Vec<Row>,
+    params: &ListParams,
+    errors: &HashMap<String, String>,
+    pagination: Option<Pagination>,
+    perm: &PermissionSet,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let links = crate::menu::default_menu();
     let ctx = ListContext {
         title: "Project".to_string(),
         head: "List of projects".to_string(),
         search: false,
+        sort: Some(params.sort()),
     };
-    let perm = crate::modules::navigation::PermissionSet {
-        browse: true,
-        read: true,
-        edit: true,
-        add: true,
-        delete: true,
-    };
-    let list = ructe_block_res!(crate::templates::navigation::table, &list, &ctx, &perm)?;
+    let statuses = ProjectStatus::all();
+    let mut cnt = ructe_block_res!(crate::templates::project::filter, params, &statuses, errors)?;
+    cnt.push_str(&ructe_block_res!(
+        crate::templates::navigation::table,
+        &list,
+        &ctx,
+        perm,
+        &pagination
+    )?);
     let meta = default_meta("List of Project");
     ructe_page_res!(
         crate::templates::navigation::frame,
         meta,
         &toplinks,
         &links,
-        &list
+        &cnt,
+        impersonating
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ListParams;
+    use chrono_tz::Tz;
+
+    fn filter(from: &str, to: &str) -> ListParams {
+        ListParams {
+            from: Some(from.to_string()),
+            to: Some(to.to_string()),
+            ..ListParams::default()
+        }
+    }
+
+    #[test]
+    fn empty_days_do_not_filter() {
+        assert_eq!(filter("", " ").dates(Tz::UTC), Ok((None, None)));
+    }
+
+    #[test]
+    fn the_to_day_is_included() {
+        let (from, to) = filter("2019-04-01", "2019-04-01").dates(Tz::UTC).unwrap();
+        assert_eq!(to.unwrap() - from.unwrap(), chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn typos_are_refused_rather_than_ignored() {
+        let errors = filter("2019-04-31", "tomorrow").dates(Tz::UTC).unwrap_err();
+        assert!(errors["from"].contains("2019-04-31"));
+        assert!(errors["to"].contains("tomorrow"));
+        let errors = filter("2019-04-02", "2019-04-01")
+            .dates(Tz::UTC)
+            .unwrap_err();
+        assert!(errors.contains_key("to"));
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ::uuid::Uuid;
+use actix::prelude::*;
+use actix_web::{ws, Error, HttpRequest, HttpResponse};
+
+use crate::db::AppState;
+use crate::modules::access::allowed;
+
+use super::data::Project;
+use super::todo::Todo;
+
+/// How often heartbeat pings are sent
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// How long before lack of client response causes a timeout
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoChange {
+    Toggle,
+    Add,
+    Edit,
+    Delete,
+    Move,
+    /// An item of the checklist was completed or reopened
+    Check,
+    /// Items were added to or removed from the checklist
+    Checklist,
+}
+
+/// A committed change of a todo, sent to every page showing the todos of the project.
+/// Serialized as `{"event": "toggle", "id": 1, "value": true}`, `value` only for toggles
+/// and `title` only for additions and edits. Moves tell which todo the moved one follows,
+/// none when it became the first. Checks name the item of the checklist of the todo.
+#[derive(Serialize, Debug, Clone)]
+pub struct TodoEvent {
+    #[serde(skip)]
+    pub project: Uuid,
+    pub event: TodoChange,
+    pub id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<i64>,
+}
+impl Message for TodoEvent {
+    type Result = ();
+}
+impl TodoEvent {
+    fn of(event: TodoChange, todo: &Todo) -> Self {
+        TodoEvent {
+            project: todo.project_id,
+            event,
+            id: todo.id,
+            value: None,
+            title: None,
+            after: None,
+            item: None,
+        }
+    }
+    pub fn toggled(todo: &Todo) -> Self {
+        TodoEvent {
+            value: Some(todo.completed),
+            ..TodoEvent::of(TodoChange::Toggle, todo)
+        }
+    }
+    pub fn added(todo: &Todo) -> Self {
+        TodoEvent {
+            title: Some(todo.title.clone()),
+            ..TodoEvent::of(TodoChange::Add, todo)
+        }
+    }
+    pub fn edited(todo: &Todo) -> Self {
+        TodoEvent {
+            title: Some(todo.title.clone()),
+            ..TodoEvent::of(TodoChange::Edit, todo)
+        }
+    }
+    pub fn deleted(project: Uuid, id: i64) -> Self {
+        TodoEvent {
+            project,
+            event: TodoChange::Delete,
+            id,
+            value: None,
+            title: None,
+            after: None,
+            item: None,
+        }
+    }
+    pub fn moved(project: Uuid, id: i64, after: Option<i64>) -> Self {
+        TodoEvent {
+            project,
+            event: TodoChange::Move,
+            id,
+            value: None,
+            title: None,
+            after,
+            item: None,
+        }
+    }
+    pub fn item_checked(project: Uuid, id: i64, item: i64, value: bool) -> Self {
+        TodoEvent {
+            project,
+            event: TodoChange::Check,
+            id,
+            value: Some(value),
+            title: None,
+            after: None,
+            item: Some(item),
+        }
+    }
+    pub fn checklist_changed(project: Uuid, id: i64) -> Self {
+        TodoEvent {
+            project,
+            event: TodoChange::Checklist,
+            id,
+            value: None,
+            title: None,
+            after: None,
+            item: None,
+        }
+    }
+}
+
+/// Sends the event to the pages of the project, call it once the change is committed
+pub fn publish(event: TodoEvent) {
+    LiveRegistry::from_registry().do_send(event);
+}
+
+/// The open websockets by project, one registry for the whole system
+#[derive(Default)]
+pub struct LiveRegistry {
+    sessions: HashMap<Uuid, HashMap<usize, Recipient<TodoEvent>>>,
+    next_id: usize,
+}
+impl Actor for LiveRegistry {
+    type Context = Context<Self>;
+}
+impl Supervised for LiveRegistry {}
+impl SystemService for LiveRegistry {}
+
+struct Join {
+    project: Uuid,
+    addr: Recipient<TodoEvent>,
+}
+impl Message for Join {
+    type Result = usize;
+}
+impl Handler<Join> for LiveRegistry {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Join, _: &mut Context<Self>) -> usize {
+        self.next_id += 1;
+        self.sessions
+            .entry(msg.project)
+            .or_insert_with(HashMap::new)
+            .insert(self.next_id, msg.addr);
+        self.next_id
+    }
+}
+
+struct Leave {
+    project: Uuid,
+    id: usize,
+}
+impl Message for Leave {
+    type Result = ();
+}
+impl Handler<Leave> for LiveRegistry {
+    type Result = ();
+
+    fn handle(&mut self, msg: Leave, _: &mut Context<Self>) {
+        let empty = match self.sessions.get_mut(&msg.project) {
+            Some(sessions) => {
+                sessions.remove(&msg.id);
+                sessions.is_empty()
+            }
+            None => false,
+        };
+        if empty {
+            self.sessions.remove(&msg.project);
+        }
+    }
+}
+
+impl Handler<TodoEvent> for LiveRegistry {
+    type Result = ();
+
+    fn handle(&mut self, msg: TodoEvent, _: &mut Context<Self>) {
+        if let Some(sessions) = self.sessions.get(&msg.project) {
+            for addr in sessions.values() {
+                if let Err(e) = addr.do_send(msg.clone()) {
+                    debug!("Live todo event not delivered: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+/// A page listening to the todos of a project
+struct LiveWs {
+    hb: Instant,
+    project: Uuid,
+    /// Given by the registry once joined
+    id: usize,
+}
+
+impl Actor for LiveWs {
+    type Context = ws::WebsocketContext<Self, AppState>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+        let addr = ctx.address();
+        LiveRegistry::from_registry()
+            .send(Join {
+                project: self.project,
+                addr: addr.recipient(),
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(id) => act.id = id,
+                    Err(_) => ctx.stop(),
+                }
+                fut::ok(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        LiveRegistry::from_registry().do_send(Leave {
+            project: self.project,
+            id: self.id,
+        });
+        Running::Stop
+    }
+}
+
+impl Handler<TodoEvent> for LiveWs {
+    type Result = ();
+
+    fn handle(&mut self, msg: TodoEvent, ctx: &mut Self::Context) {
+        match serde_json::to_string(&msg) {
+            Ok(text) => ctx.text(text),
+            Err(e) => error!("Serializing {:?} failed: {:?}", msg, e),
+        }
+    }
+}
+
+/// Changes are made over http, the socket only listens
+impl StreamHandler<ws::Message, ws::ProtocolError> for LiveWs {
+    fn handle(&mut self, msg: ws::Message, ctx: &mut Self::Context) {
+        match msg {
+            ws::Message::Ping(msg) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            ws::Message::Pong(_) => {
+                self.hb = Instant::now();
+            }
+            ws::Message::Text(_) | ws::Message::Binary(_) => {}
+            ws::Message::Close(_) => {
+                ctx.stop();
+            }
+        }
+    }
+}
+
+impl LiveWs {
+    fn new(project: Uuid) -> Self {
+        Self {
+            hb: Instant::now(),
+            project,
+            id: 0,
+        }
+    }
+
+    /// Pings the client and drops it once it stopped answering
+    fn hb(&self, ctx: &mut <Self as Actor>::Context) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                debug!("Live todo client heartbeat failed, disconnecting!");
+                ctx.stop();
+                return;
+            }
+            ctx.ping("");
+        });
+    }
+}
+
+/// Opens the websocket of a project the user can read
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let org = match crate::modules::team::current(req)? {
+        Some(org_id) => match crate::modules::team::load(req, org_id) {
+            Ok(org) => org,
+            Err(_) => return Ok(HttpResponse::NotFound().finish()),
+        },
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    if !allowed(req, org.access_control_id).read {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    match Project::load(req, org.id, pid) {
+        Ok(project) => ws::start(req, LiveWs::new(project.uuid)),
+        Err(_) => Ok(HttpResponse::NotFound().finish()),
+    }
+}
@@ -0,0 +1,151 @@
+use actix_web::{error, Error, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use diesel::prelude::*;
+use futures::future::Future;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::db::{AppState, SQuery};
+use crate::modules::meta::default_meta;
+use crate::modules::navigation::Link;
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
+use crate::render::Failure;
+use crate::schema::{projects, todos};
+use crate::utils::http_ok;
+
+use super::data::Project;
+use super::todo::Todo;
+
+/// A todo of one of the teams of the user, with the project and team it belongs to
+#[derive(Debug, Clone, Serialize)]
+pub struct AssignedTodo {
+    pub team_id: i64,
+    pub team: String,
+    pub project: Project,
+    pub todo: Todo,
+}
+
+/// Which todos of the teams of the user are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// Assigned to the user, open ones first
+    Mine,
+    /// Open and past their due date, whoever they are assigned to, the oldest first
+    Overdue,
+}
+
+/// The todos of the selection in the projects of every team the user is a member of
+pub fn load(
+    req: &HttpRequest<AppState>,
+    usr_id: i64,
+    selection: Selection,
+    now: &DateTime<Utc>,
+) -> Result<Vec<AssignedTodo>, Error> {
+    let teams: HashMap<i64, String> = crate::modules::team::for_user(req, usr_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|org| (org.id, org.title))
+        .collect();
+    let team_ids: Vec<i64> = teams.keys().cloned().collect();
+    let query = todos::table
+        .inner_join(projects::table)
+        .filter(todos::deleted_at.is_null())
+        .filter(projects::team_id.eq_any(team_ids))
+        .filter(projects::deleted_at.is_null())
+        .filter(projects::is_template.eq(false))
+        .select((todos::all_columns, projects::all_columns))
+        .into_boxed();
+    let query = match selection {
+        Selection::Mine => query.filter(todos::assignee_id.eq(usr_id)).order((
+            todos::completed.asc(),
+            projects::team_id.asc(),
+            projects::projectid.asc(),
+            todos::title.asc(),
+        )),
+        Selection::Overdue => query
+            .filter(todos::completed.eq(false))
+            .filter(todos::due_at.lt(*now))
+            .order((
+                todos::due_at.asc(),
+                todos::priority.desc(),
+                todos::title.asc(),
+            )),
+    };
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<(Todo, Project)>,
+    };
+    let res = match req.state().rdb.send(select).wait() {
+        Ok(Ok(res)) => res,
+        e => {
+            error!("Loading the todos of {} failed: {:?}", usr_id, e);
+            return Err(error::ErrorInternalServerError("loading the todos failed"));
+        }
+    };
+    Ok(res
+        .into_iter()
+        .map(|(todo, project)| AssignedTodo {
+            team_id: project.team_id,
+            team: teams.get(&project.team_id).cloned().unwrap_or_default(),
+            project,
+            todo,
+        })
+        .collect())
+}
+
+fn index_render(
+    data: &[AssignedTodo],
+    selection: Selection,
+    now: &DateTime<Utc>,
+    tz: Tz,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = vec![
+        Link::new("My todos", "/todo/mine"),
+        Link::new("Overdue todos", "/todo/overdue"),
+    ];
+    let heading = match selection {
+        Selection::Mine => "Todos assigned to me",
+        Selection::Overdue => "Overdue todos of my teams",
+    };
+    let cnt = ructe_block_res!(crate::templates::project::mine, data, heading, now, tz)?;
+    let meta = default_meta(heading);
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+fn render(req: &HttpRequest<AppState>, selection: Selection) -> Result<HttpResponse, Error> {
+    let usr_meta = match UserMeta::current(req) {
+        Ok(usr_meta) => usr_meta,
+        Err(_) => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let now = Utc::now();
+    let data = load(req, usr_meta.user_id, selection, &now)?;
+    http_ok(index_render(
+        &data,
+        selection,
+        &now,
+        timezone::current(req),
+        &req.impersonating(),
+    ))
+}
+
+/// The todos assigned to the user across their teams
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    render(req, Selection::Mine)
+}
+
+/// The overdue todos across the projects of the user
+pub fn overdue(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    render(req, Selection::Overdue)
+}
@@ -1,10 +1,21 @@
 pub mod add;
+pub mod checklist;
+pub mod comments;
 pub mod data;
+pub mod delete;
 pub mod edit;
+pub mod export;
+pub mod history;
+pub mod import;
 pub mod list;
+pub mod live;
+pub mod mine;
+pub mod order;
+pub mod template;
 pub mod todo;
 pub mod todo_list;
 pub mod todo_register;
+pub mod view;
 
 // use actix_web::Error;
 // use actix_web::FromRequest;
@@ -16,12 +27,32 @@ use actix_web::HttpRequest;
 // use crate::utils::http_ok;
 
 use crate::db::{AppState, DbExecutorError, SQuery};
+use crate::modules::navigation::PermissionSet;
 use crate::modules::project::data::Project;
 use crate::schema::projects::dsl::*;
 use futures::future::Future;
 
 // use crate::modules::meta::default_meta;
 
+/// A change of the projects of a team, the create, edit and delete handlers each need their own
+/// permission on the team
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Create,
+    Edit,
+    Delete,
+}
+impl Change {
+    /// Whether the permissions of the user on the team allow the change
+    pub fn allowed(self, perm: &PermissionSet) -> bool {
+        match self {
+            Change::Create => perm.add,
+            Change::Edit => perm.edit,
+            Change::Delete => perm.delete,
+        }
+    }
+}
+
 // fn index_render() -> Result<String, Failure> {
 //     let meta = default_meta("Project page");
 //     ructe_page_res!(ecs::templates::hello, meta, "Project block")
@@ -49,7 +80,10 @@ impl Project {
     ) -> Result<Project, DbExecutorError> {
         use diesel::prelude::*;
         use std::marker::PhantomData;
-        let query = projects.filter(projectid.eq(eid)).filter(team_id.eq(oid));
+        let query = projects
+            .filter(projectid.eq(eid))
+            .filter(team_id.eq(oid))
+            .filter(deleted_at.is_null());
         let select = SQuery {
             select: query,
             phantom: PhantomData::<Project>,
@@ -79,3 +113,107 @@ impl Project {
         Err(DbExecutorError::Unknown)
     }
 }
+
+/// Gives out the next project number of the team. The counter row is locked by the upsert
+/// until the transaction it runs in ends, so concurrent adds always get distinct numbers.
+/// Numbering starts from 0 as it did before there were counters.
+pub fn next_projectid(conn: &diesel::pg::PgConnection, org_id: i64) -> diesel::QueryResult<i64> {
+    use crate::schema::team_project_counters::dsl as counters;
+    use diesel::prelude::*;
+    diesel::insert_into(counters::team_project_counters)
+        .values((counters::team_id.eq(org_id), counters::last_projectid.eq(0)))
+        .on_conflict(counters::team_id)
+        .do_update()
+        .set(counters::last_projectid.eq(counters::last_projectid + 1))
+        .returning(counters::last_projectid)
+        .get_result(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Change;
+    use crate::modules::access::{Grant, GrantSource, PermissionMap};
+    use crate::modules::navigation::PermissionSet;
+    use crate::modules::team::role::Role;
+
+    const TEAM_ACID: i64 = 42;
+
+    /// What `allowed` hands the handlers for a member of the team in `role`: the rules the
+    /// role expands into, resolved as `PermissionCheck` does
+    fn team_permission(role: Role, archived: bool) -> PermissionSet {
+        let grants: Vec<Grant> = role
+            .access_types()
+            .iter()
+            .enumerate()
+            .map(|(idx, tp)| Grant {
+                access_control_id: TEAM_ACID,
+                access_type: (*tp).to_string(),
+                source: GrantSource::Rule {
+                    rule_id: idx as i64,
+                    group_id: 7,
+                    group_name: format!("team:1:{}", role.as_str()),
+                },
+            })
+            .collect();
+        let mut pmap = PermissionMap::from_grants(&grants);
+        if archived {
+            pmap.restrict_to_read(&[TEAM_ACID]);
+        }
+        pmap.map()
+            .get(&TEAM_ACID)
+            .cloned()
+            .unwrap_or_else(PermissionSet::deny)
+    }
+
+    fn changes(role: Role, archived: bool) -> Vec<Change> {
+        let perm = team_permission(role, archived);
+        vec![Change::Create, Change::Edit, Change::Delete]
+            .into_iter()
+            .filter(|change| change.allowed(&perm))
+            .collect()
+    }
+
+    #[test]
+    fn viewers_change_no_project() {
+        assert!(changes(Role::Viewer, false).is_empty());
+    }
+
+    #[test]
+    fn members_create_and_edit_but_do_not_delete() {
+        assert_eq!(
+            changes(Role::Member, false),
+            vec![Change::Create, Change::Edit]
+        );
+    }
+
+    #[test]
+    fn admins_make_every_change() {
+        assert_eq!(
+            changes(Role::Admin, false),
+            vec![Change::Create, Change::Edit, Change::Delete]
+        );
+    }
+
+    #[test]
+    fn owners_make_every_change() {
+        assert_eq!(
+            changes(Role::Owner, false),
+            vec![Change::Create, Change::Edit, Change::Delete]
+        );
+    }
+
+    #[test]
+    fn nobody_changes_the_projects_of_an_archived_team() {
+        for role in Role::all() {
+            assert!(changes(role, true).is_empty(), "{:?}", role);
+        }
+    }
+
+    #[test]
+    fn outsiders_change_no_project() {
+        let perm = PermissionSet::deny();
+        assert!(!Change::Create.allowed(&perm));
+        assert!(!Change::Edit.allowed(&perm));
+        assert!(!Change::Delete.allowed(&perm));
+    }
+}
@@ -0,0 +1,245 @@
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use ::uuid::Uuid;
+use diesel::dsl::max;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::db::AppState;
+use crate::schema::{projects, todos};
+use crate::utils::write_conn;
+
+use super::data::Project;
+use super::live::{self, TodoEvent};
+
+/// The space left between the positions of todos, a todo moved between two neighbours takes
+/// the middle of their positions so only its own row changes
+pub const GAP: i64 = 1024;
+
+/// Where a todo is dropped, next to the todo it is shown after or before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    After(i64),
+    Before(i64),
+}
+
+/// The new place of a moved todo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Moved {
+    pub position: i64,
+    /// The todo it is shown after, `None` when it is the first one
+    pub after: Option<i64>,
+}
+
+/// Locks the project row until the end of the transaction, changes of the order of its todos
+/// are applied one after the other so concurrent moves end up in the same order for everyone
+pub fn lock(conn: &PgConnection, project: Uuid) -> QueryResult<()> {
+    projects::table
+        .filter(projects::uuid.eq(project))
+        .select(projects::uuid)
+        .for_update()
+        .first::<Uuid>(conn)
+        .map(|_| ())
+}
+
+/// Positions after the last todo of the project for `count` new todos, call it in the
+/// transaction inserting them
+pub fn append_positions(conn: &PgConnection, project: Uuid, count: usize) -> QueryResult<Vec<i64>> {
+    lock(conn, project)?;
+    let last: Option<i64> = todos::table
+        .filter(todos::project_id.eq(project))
+        .select(max(todos::position))
+        .first(conn)?;
+    let last = last.unwrap_or(0);
+    Ok((1..=count as i64).map(|k| last + k * GAP).collect())
+}
+
+/// The todos of the project in their order, as pairs of id and position
+fn ordered(conn: &PgConnection, project: Uuid, except: i64) -> QueryResult<Vec<(i64, i64)>> {
+    todos::table
+        .filter(todos::project_id.eq(project))
+        .filter(todos::deleted_at.is_null())
+        .filter(todos::id.ne(except))
+        .order((todos::position.asc(), todos::id.asc()))
+        .select((todos::id, todos::position))
+        .load(conn)
+}
+
+/// Spaces the positions out `GAP` apart in the same order, the todos whose position changed
+/// are returned with their new one
+fn respace(order: &mut [(i64, i64)]) -> Vec<(i64, i64)> {
+    let mut changed = Vec::new();
+    for (idx, (todo_id, pos)) in order.iter_mut().enumerate() {
+        let spaced = (idx as i64 + 1) * GAP;
+        if *pos != spaced {
+            *pos = spaced;
+            changed.push((*todo_id, spaced));
+        }
+    }
+    changed
+}
+
+/// Spaces the todos out again, once there is no room left between two of them
+fn rebalance(conn: &PgConnection, order: &mut [(i64, i64)]) -> QueryResult<()> {
+    for (todo_id, pos) in respace(order) {
+        diesel::update(todos::table.filter(todos::id.eq(todo_id)))
+            .set(todos::position.eq(pos))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// The position between the neighbours at `idx - 1` and `idx`, `None` when there is no room
+fn between(order: &[(i64, i64)], idx: usize) -> Option<i64> {
+    let lower = if idx > 0 {
+        Some(order[idx - 1].1)
+    } else {
+        None
+    };
+    let upper = order.get(idx).map(|(_, pos)| *pos);
+    match (lower, upper) {
+        (Some(lower), Some(upper)) if upper - lower >= 2 => Some(lower + (upper - lower) / 2),
+        (Some(_), Some(_)) => None,
+        (Some(lower), None) => Some(lower + GAP),
+        (None, Some(upper)) => Some(upper - GAP),
+        (None, None) => Some(GAP),
+    }
+}
+
+/// Moves the todo next to its new neighbour. The neighbour is looked up when the lock is held,
+/// so a move made meanwhile by someone else is taken into account instead of overwritten.
+/// `None` when the todo or the neighbour is not an active todo of the project.
+pub fn move_todo(
+    conn: &PgConnection,
+    project: Uuid,
+    todo_id: i64,
+    placement: Placement,
+) -> QueryResult<Option<Moved>> {
+    conn.transaction(|| {
+        lock(conn, project)?;
+        let found: Option<i64> = todos::table
+            .filter(todos::id.eq(todo_id))
+            .filter(todos::project_id.eq(project))
+            .filter(todos::deleted_at.is_null())
+            .select(todos::id)
+            .first(conn)
+            .optional()?;
+        if found.is_none() {
+            return Ok(None);
+        }
+        let mut order = ordered(conn, project, todo_id)?;
+        let anchor = match placement {
+            Placement::After(other) | Placement::Before(other) => other,
+        };
+        let idx = match order.iter().position(|(other, _)| *other == anchor) {
+            Some(idx) => match placement {
+                Placement::After(_) => idx + 1,
+                Placement::Before(_) => idx,
+            },
+            None => return Ok(None),
+        };
+        let position = match between(&order, idx) {
+            Some(position) => position,
+            None => {
+                rebalance(conn, &mut order)?;
+                between(&order, idx).unwrap_or_else(|| (idx as i64 + 1) * GAP)
+            }
+        };
+        diesel::update(todos::table.filter(todos::id.eq(todo_id)))
+            .set(todos::position.eq(position))
+            .execute(conn)?;
+        Ok(Some(Moved {
+            position,
+            after: if idx > 0 {
+                Some(order[idx - 1].0)
+            } else {
+                None
+            },
+        }))
+    })
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReorderParams {
+    id: i64,
+    /// The todo shown right above the dropped one, when there is one
+    after: Option<i64>,
+    /// The todo shown right below the dropped one, used when there is nothing above
+    before: Option<i64>,
+}
+/// Moves a todo of the list, answers with its new place
+pub fn reorder(
+    (req, form): (HttpRequest<AppState>, Form<ReorderParams>),
+) -> Result<HttpResponse, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let placement = match (form.after, form.before) {
+        (Some(after), _) => Placement::After(after),
+        (None, Some(before)) => Placement::Before(before),
+        (None, None) => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    let project = match crate::modules::team::writable(&req)? {
+        Some(org_id) if !crate::modules::team::permission(&req, org_id).edit => {
+            return Ok(HttpResponse::Forbidden().finish());
+        }
+        Some(org_id) => match Project::load(&req, org_id, pid) {
+            Ok(project) => project,
+            Err(_) => return Ok(HttpResponse::NotFound().finish()),
+        },
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let conn = write_conn(&req)?;
+    match move_todo(&conn, project.uuid, form.id, placement) {
+        Ok(Some(moved)) => {
+            live::publish(TodoEvent::moved(project.uuid, form.id, moved.after));
+            Ok(HttpResponse::Ok().json(moved))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().finish()),
+        Err(e) => {
+            error!(
+                "Moving todo {} of project {} failed: {:?}",
+                form.id, project.projectid, e
+            );
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{between, respace, GAP};
+
+    #[test]
+    fn positions_between_neighbours() {
+        let order = [(1, 1024), (2, 2048), (3, 2050), (4, 2051)];
+        let cases = [
+            // before the first one
+            (0, Some(1024 - GAP)),
+            (1, Some(1536)),
+            (2, Some(2049)),
+            // no room left between 2050 and 2051
+            (3, None),
+            // after the last one
+            (4, Some(2051 + GAP)),
+        ];
+        for (idx, expected) in cases.iter() {
+            assert_eq!(between(&order, *idx), *expected, "{}", idx);
+        }
+        assert_eq!(between(&[], 0), Some(GAP));
+    }
+
+    #[test]
+    fn respacing_keeps_the_order() {
+        let mut order = [(7, 1024), (3, 1025), (9, 1026), (5, 4096)];
+        let changed = respace(&mut order);
+        assert_eq!(order, [(7, 1024), (3, 2048), (9, 3072), (5, 4096)]);
+        assert_eq!(changed, vec![(3, 2048), (9, 3072)]);
+        // there is room between every two of them afterwards
+        for idx in 1..order.len() {
+            assert!(between(&order, idx).is_some());
+        }
+        assert!(respace(&mut order).is_empty());
+    }
+}
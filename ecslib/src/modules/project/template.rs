@@ -0,0 +1,245 @@
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use chrono::Duration;
+use chrono_tz::Tz;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Uuid as SqlUuid;
+use futures::future::Future;
+use std::marker::PhantomData;
+
+use crate::db::{AppState, SQuery};
+use crate::modules::access::allowed;
+use crate::modules::meta::default_meta;
+use crate::modules::team::data::Team;
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::render::Failure;
+use crate::schema::projects;
+use crate::utils::{http_ok, write_conn};
+
+use super::data::Project;
+
+/// Copies the todos of a project which are not deleted, none of them completed.
+/// Priorities and the manual order are kept, due dates belong to the schedule of the original.
+const COPY_TODOS_SQL: &str = r#"
+INSERT INTO todos (title, description, email, phone, project_id, completed, priority, position,
+  checklist_mode)
+SELECT title, description, email, phone, $2, FALSE, priority, position, checklist_mode
+FROM todos
+WHERE project_id = $1 AND deleted_at IS NULL
+ORDER BY position, id
+"#;
+
+/// Copies the checklists along, every item open. The copies were inserted in the order of the
+/// originals, so the n-th todo of the copy by position and id is the copy of the n-th original.
+const COPY_ITEMS_SQL: &str = r#"
+INSERT INTO todo_items (todo_id, title, completed, position)
+SELECT copy.id, item.title, FALSE, item.position
+FROM (SELECT id, row_number() OVER (ORDER BY position, id) AS rank
+      FROM todos WHERE project_id = $1 AND deleted_at IS NULL) AS original
+JOIN (SELECT id, row_number() OVER (ORDER BY position, id) AS rank
+      FROM todos WHERE project_id = $2) AS copy ON copy.rank = original.rank
+JOIN todo_items item ON item.todo_id = original.id
+ORDER BY copy.id, item.position, item.id
+"#;
+
+/// The history of the copied todos starts with their creation
+const COPY_EVENTS_SQL: &str = r#"
+INSERT INTO todo_events (todo_id, event)
+SELECT id, 'created' FROM todos WHERE project_id = $1
+"#;
+
+/// Creates a copy of the project in the team, with a new number and its todos and their
+/// checklists reset.
+/// The dates are shifted by `offset` and the copy is a template when `as_template` is set.
+pub fn clone_project(
+    conn: &PgConnection,
+    source: &Project,
+    org_id: i64,
+    new_title: &str,
+    offset: Duration,
+    as_template: bool,
+) -> QueryResult<Project> {
+    conn.transaction(|| {
+        let number = super::next_projectid(conn, org_id)?;
+        let copy: Project = diesel::insert_into(projects::table)
+            .values((
+                projects::projectid.eq(number),
+                projects::team_id.eq(org_id),
+                projects::uuid.eq(::uuid::Uuid::new_v4()),
+                projects::title.eq(new_title),
+                projects::content.eq(&source.content),
+                projects::start_date.eq(source.start_date.map(|date| date + offset)),
+                projects::end_date.eq(source.end_date.map(|date| date + offset)),
+                projects::is_template.eq(as_template),
+            ))
+            .get_result(conn)?;
+        diesel::sql_query(COPY_TODOS_SQL)
+            .bind::<SqlUuid, _>(source.uuid)
+            .bind::<SqlUuid, _>(copy.uuid)
+            .execute(conn)?;
+        diesel::sql_query(COPY_ITEMS_SQL)
+            .bind::<SqlUuid, _>(source.uuid)
+            .bind::<SqlUuid, _>(copy.uuid)
+            .execute(conn)?;
+        diesel::sql_query(COPY_EVENTS_SQL)
+            .bind::<SqlUuid, _>(copy.uuid)
+            .execute(conn)?;
+        Ok(copy)
+    })
+}
+
+/// The selected team when projects can be added to it, together with the project from the path
+fn source(req: &HttpRequest<AppState>) -> Result<Option<(Team, Project)>, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(None),
+    };
+    if let Some(org_id) = crate::modules::team::writable(req)? {
+        if let Ok(org) = crate::modules::team::load(req, org_id) {
+            if allowed(req, org.access_control_id).add {
+                if let Ok(project) = Project::load(req, org.id, pid) {
+                    return Ok(Some((org, project)));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Saves a copy of the project as a template of the team
+pub fn save_template(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let (org, project) = match source(req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let conn = write_conn(req)?;
+    let res = clone_project(
+        &conn,
+        &project,
+        org.id,
+        &project.title,
+        Duration::zero(),
+        true,
+    );
+    debug!("{:?}", res);
+    Ok(HttpResponse::Found()
+        .header("location", "/project/templates")
+        .finish())
+}
+
+fn clone_render(
+    project: &Project,
+    tz: Tz,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let cnt = ructe_block_res!(crate::templates::project::clone_form, project, tz)?;
+    let meta = default_meta("New Project");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+/// Asks for the title of the new project and how many days its dates are to be moved
+pub fn clone_form(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    match source(req)? {
+        Some((_, project)) => http_ok(clone_render(
+            &project,
+            timezone::current(req),
+            &req.impersonating(),
+        )),
+        None => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CloneParams {
+    title: String,
+    offset_days: Option<String>,
+}
+/// Creates a new project from a template or another project
+pub fn save_clone(
+    (req, form): (HttpRequest<AppState>, Form<CloneParams>),
+) -> Result<HttpResponse, Error> {
+    let (org, project) = match source(&req)? {
+        Some(found) => found,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let new_title = form.title.trim();
+    if new_title.is_empty() {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+    let offset = match form.offset_days.as_ref().map(|d| d.trim()) {
+        None | Some("") => Duration::zero(),
+        Some(days) => match days.parse::<i64>() {
+            Ok(days) => Duration::days(days),
+            Err(_) => return Ok(HttpResponse::BadRequest().finish()),
+        },
+    };
+    let conn = write_conn(&req)?;
+    match clone_project(&conn, &project, org.id, new_title, offset, false) {
+        Ok(copy) => Ok(HttpResponse::Found()
+            .header("location", format!("/project/{}", copy.projectid))
+            .finish()),
+        Err(e) => {
+            error!("Cloning project {} failed: {:?}", project.projectid, e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+fn index_render(
+    data: &[Project],
+    tz: Tz,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let cnt = ructe_block_res!(crate::templates::project::templates, data, tz)?;
+    let meta = default_meta("Project Templates");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+/// The templates of the selected team
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let org_id = match crate::modules::team::current(req)? {
+        Some(org_id) => org_id,
+        None => {
+            return Ok(HttpResponse::Found()
+                .header("location", "/team/select")
+                .finish())
+        }
+    };
+    let query = projects::table
+        .filter(projects::team_id.eq(org_id))
+        .filter(projects::deleted_at.is_null())
+        .filter(projects::is_template.eq(true))
+        .order(projects::title.asc());
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<Project>,
+    };
+    match req.state().rdb.send(select).wait() {
+        Ok(Ok(data)) => http_ok(index_render(
+            &data,
+            timezone::current(req),
+            &req.impersonating(),
+        )),
+        _ => Ok(HttpResponse::InternalServerError().finish()),
+    }
+}
@@ -1,6 +1,6 @@
-//use actix_web::middleware::session::RequestSession;
 use actix_web::{Error, Form, FromRequest, HttpRequest, HttpResponse, Path};
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
 // HttpMessage, Query, Json };
 use futures::future::Future;
@@ -8,19 +8,37 @@ use futures::future::Future;
 use ::uuid::Uuid;
 // use pretty_env_logger;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
 
-use crate::db::{AppState, DbExecutorError, SQuery, WQuery};
+use crate::db::{AppState, DbExecutorError, SQuery};
 use crate::modules::navigation::Link;
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
 use crate::render::Failure;
-use crate::utils::http_ok;
+use crate::utils::{http_ok, write_conn};
 
 use crate::modules::meta::default_meta;
+use crate::modules::project::checklist::{self, ChecklistMode, TodoItem};
 use crate::modules::project::data::Project;
+use crate::modules::project::history::{self, EventKind};
+use crate::modules::team::role::{self, Member};
 use crate::schema::todos;
 use crate::schema::todos::dsl::*;
 
-fn index_render(data: Vec<Todo>, project: Project) -> Result<String, Failure> {
+#[allow(clippy::too_many_arguments)]
+fn index_render(
+    data: Vec<Todo>,
+    project: Project,
+    items: &HashMap<i64, Vec<TodoItem>>,
+    members: &[Member],
+    assignee: &str,
+    sort: &str,
+    tz: Tz,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     // let toplinks = Vec::new();
     let links = vec![
@@ -30,10 +48,21 @@ fn index_render(data: Vec<Todo>, project: Project) -> Result<String, Failure> {
         ),
         Link::new("Todo list", &format!("/project/{}/todo", project.projectid)),
     ];
-    let mut list = ructe_block_res!(crate::templates::project::todo, &data, &project)?;
+    let mut list = ructe_block_res!(
+        crate::templates::project::todo,
+        &data,
+        &project,
+        items,
+        members,
+        assignee,
+        sort,
+        &Utc::now(),
+        tz
+    )?;
     // let scr = ecs::modules::Script::new("/static/todo.js");
     // list.push_str(scr.as_html()?.as_ref());
     list.push_str(r#"<script src="/static/todo.js" charset="utf-8"></script>"#);
+    list.push_str(r#"<script src="/static/orderlist.js" charset="utf-8"></script>"#);
     let mut meta = default_meta("project todo");
     meta.add_local_css("/static/matswitch.css");
     ructe_page_res!(
@@ -41,7 +70,8 @@ fn index_render(data: Vec<Todo>, project: Project) -> Result<String, Failure> {
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
 
@@ -50,15 +80,35 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
         .unwrap()
         .parse::<i64>()
         .unwrap();
-    if let Ok(orgids) = std::env::var("todo_ORG") {
-        let orgid = orgids
-            .parse::<i64>()
-            .unwrap_or_else(|_| panic!("{} must be int", "todo_ORG"));
+    if let Some(orgid) = crate::modules::team::current(req)? {
         debug!("{},{}", orgid, ecs);
         if let Ok(project) = Project::load(req, orgid, ecs) {
-            let query = todos.filter(project_id.eq(project.uuid));
-            // TODO move ordering to clilent side
-            let query = query.order(title.asc());
+            let assignee = req.query().get("assignee").cloned().unwrap_or_default();
+            let query = todos
+                .filter(project_id.eq(project.uuid))
+                .filter(deleted_at.is_null())
+                .into_boxed();
+            let query = match assignee.as_str() {
+                "" => query,
+                "none" => query.filter(assignee_id.is_null()),
+                "me" => match UserMeta::current(req) {
+                    Ok(usr_meta) => query.filter(assignee_id.eq(usr_meta.user_id)),
+                    Err(_) => return Ok(HttpResponse::Forbidden().finish()),
+                },
+                other => match other.parse::<i64>() {
+                    Ok(usr_id) => query.filter(assignee_id.eq(usr_id)),
+                    Err(_) => return Ok(HttpResponse::BadRequest().finish()),
+                },
+            };
+            // todos without a due date come last, postgres sorts nulls after the dated ones
+            let sort = req.query().get("sort").cloned().unwrap_or_default();
+            let query = match sort.as_str() {
+                "due" => query.order((due_at.asc(), priority.desc(), title.asc())),
+                "priority" => query.order((priority.desc(), due_at.asc(), title.asc())),
+                "title" => query.order(title.asc()),
+                // the order the todos were arranged in, see `order::move_todo`
+                _ => query.order((position.asc(), id.asc())),
+            };
 
             let select = SQuery {
                 select: query,
@@ -74,11 +124,23 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
                 .unwrap()
                 .unwrap();
 
+            let members = role::members(req, orgid).unwrap_or_default();
+            let items = checklist::for_todos(req, res.iter().map(|todo| todo.id).collect())
+                .unwrap_or_default();
             debug!("{:?}", project);
-            return http_ok(index_render(res, project));
+            return http_ok(index_render(
+                res,
+                project,
+                &items,
+                &members,
+                &assignee,
+                &sort,
+                timezone::current(req),
+                &req.impersonating(),
+            ));
         }
     } else {
-        let org_select = "/org/select".to_owned();
+        let org_select = "/team/select".to_owned();
         return Ok(HttpResponse::Found()
             .header("location", org_select)
             .finish());
@@ -86,6 +148,47 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Completes or reopens a todo of the project and records it in the history, in one transaction.
+/// Todos already in the state asked for are left alone, and `None` is returned when the
+/// checklist of the todo decides its completion.
+pub fn set_completion(
+    conn: &PgConnection,
+    project_uuid: Uuid,
+    aid: i64,
+    value: bool,
+    usr_id: Option<i64>,
+) -> QueryResult<Option<Vec<Todo>>> {
+    conn.transaction(|| {
+        let todo: Option<Todo> = todos
+            .filter(id.eq(aid))
+            .filter(project_id.eq(project_uuid))
+            .filter(deleted_at.is_null())
+            .first(conn)
+            .optional()?;
+        match todo {
+            Some(ref todo) if !checklist::may_complete(conn, todo, value)? => {
+                return Ok(None);
+            }
+            _ => {}
+        }
+        let target = todos
+            .filter(id.eq(aid))
+            .filter(project_id.eq(project_uuid))
+            .filter(deleted_at.is_null())
+            .filter(completed.ne(value));
+        let updated: Vec<Todo> = diesel::update(target)
+            .set((
+                completed.eq(value),
+                completed_at.eq(if value { Some(Utc::now()) } else { None }),
+                completed_by.eq(if value { usr_id } else { None }),
+            ))
+            .get_results(conn)?;
+        let ids: Vec<i64> = updated.iter().map(|todo| todo.id).collect();
+        history::record(conn, &ids, EventKind::of_completion(value), usr_id)?;
+        Ok(Some(updated))
+    })
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ToggleParams {
     id: i64,
@@ -100,21 +203,36 @@ pub fn toggle((req, form): (HttpRequest<AppState>, Form<ToggleParams>)) -> HttpR
     // let aid = form.id.parse::<i64>().unwrap();
     let value = form.value.parse::<bool>().unwrap();
     let aid = form.id;
-    let query = diesel::update(todos.filter(id.eq(aid))).set(completed.eq(value));
-    let upd = WQuery {
-        query,
-        phantom: PhantomData::<Todo>,
-    };
-    let res = req
-        .state()
-        .wdb
-        .send(upd)
-        .map_err(actix_web::Error::from)
-        .wait()
-        .ok()
+    let ecs = Path::<String>::extract(&req)
         .unwrap()
+        .parse::<i64>()
         .unwrap();
-    debug!("{:?}", res);
+    if let Ok(Some(orgid)) = crate::modules::team::writable(&req) {
+        if !crate::modules::team::permission(&req, orgid).edit {
+            return HttpResponse::Forbidden().finish();
+        }
+        if let Ok(project) = Project::load(&req, orgid, ecs) {
+            let usr_id = UserMeta::current(&req)
+                .ok()
+                .map(|usr_meta| usr_meta.user_id);
+            let conn = match write_conn(&req) {
+                Ok(conn) => conn,
+                Err(e) => return HttpResponse::from_error(e),
+            };
+            let res = set_completion(&conn, project.uuid, aid, value, usr_id);
+            debug!("{:?}", res);
+            match res {
+                Ok(Some(updated)) => {
+                    for todo in updated.iter() {
+                        super::live::publish(super::live::TodoEvent::toggled(todo));
+                    }
+                }
+                // the checklist decides, the page is out of date
+                Ok(None) => return HttpResponse::Conflict().finish(),
+                Err(_) => {}
+            }
+        }
+    }
     // HttpResponse::Found().finish()
     // Ok(HttpResponse::Found().header("location", "../invalid").finish())
     HttpResponse::Found().header("location", "./todo").finish()
@@ -148,13 +266,91 @@ pub struct Todo {
     pub phone: Option<String>,
     pub project_id: Uuid,
     pub completed: bool,
-    pub completed_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub completed_by: Option<i64>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub assignee_id: Option<i64>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: i16,
+    pub position: i64,
+    pub checklist_mode: String,
+}
+
+/// How urgent a todo is, stored as its number in `todos.priority`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    Urgent,
+}
+impl Priority {
+    pub fn all() -> Vec<Priority> {
+        vec![
+            Priority::Low,
+            Priority::Normal,
+            Priority::High,
+            Priority::Urgent,
+        ]
+    }
+    pub fn value(self) -> i16 {
+        match self {
+            Priority::Low => 0,
+            Priority::Normal => 1,
+            Priority::High => 2,
+            Priority::Urgent => 3,
+        }
+    }
+    /// The priority stored as `value`, values out of range are the closest priority
+    pub fn of(value: i16) -> Priority {
+        match value {
+            v if v <= 0 => Priority::Low,
+            1 => Priority::Normal,
+            2 => Priority::High,
+            _ => Priority::Urgent,
+        }
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+    pub fn parse(name: &str) -> Option<Priority> {
+        Priority::all().into_iter().find(|p| p.as_str() == name)
+    }
+}
+impl Default for Priority {
+    fn default() -> Self {
+        Priority::Normal
+    }
 }
 
 impl Todo {
-    pub fn load(req: &HttpRequest<AppState>, aid: i64) -> Result<Todo, DbExecutorError> {
+    pub fn priority(&self) -> Priority {
+        Priority::of(self.priority)
+    }
+    pub fn checklist_mode(&self) -> ChecklistMode {
+        ChecklistMode::parse(&self.checklist_mode).unwrap_or_default()
+    }
+    /// Open todos past their due date
+    pub fn is_overdue(&self, now: &DateTime<Utc>) -> bool {
+        !self.completed && self.due_at.map_or(false, |due| due < *now)
+    }
+
+    pub fn load(
+        req: &HttpRequest<AppState>,
+        project: Uuid,
+        aid: i64,
+    ) -> Result<Todo, DbExecutorError> {
         use diesel::prelude::*;
-        let query = todos.filter(id.eq(aid));
+        let query = todos
+            .filter(id.eq(aid))
+            .filter(project_id.eq(project))
+            .filter(deleted_at.is_null());
         let select = SQuery {
             select: query,
             phantom: PhantomData::<Todo>,
@@ -166,3 +362,28 @@ impl Todo {
         Err(DbExecutorError::Unknown)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Priority;
+
+    #[test]
+    fn stored_priorities() {
+        let cases = [
+            (i16::min_value(), Priority::Low),
+            (-1, Priority::Low),
+            (0, Priority::Low),
+            (1, Priority::Normal),
+            (2, Priority::High),
+            (3, Priority::Urgent),
+            (4, Priority::Urgent),
+            (i16::max_value(), Priority::Urgent),
+        ];
+        for (value, expected) in cases.iter() {
+            assert_eq!(Priority::of(*value), *expected, "{}", value);
+        }
+        for priority in Priority::all() {
+            assert_eq!(Priority::of(priority.value()), priority);
+        }
+    }
+}
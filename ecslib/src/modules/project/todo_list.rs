@@ -1,11 +1,13 @@
-use actix_web::middleware::session::RequestSession;
 use actix_web::{Error, HttpRequest, HttpResponse};
+use chrono_tz::Tz;
 use diesel::prelude::*;
 use futures::future::Future;
 use std::marker::PhantomData;
 
 use crate::db::{AppState, SQuery};
 use crate::modules::navigation::{Cell, CellContent, Link, ListContext, Permission, Row};
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
 use crate::render::Failure;
 use crate::utils::http_ok;
 
@@ -13,7 +15,7 @@ use super::data::Project;
 use crate::modules::meta::default_meta;
 use crate::schema::projects::dsl::*;
 
-fn create_list(data: &[Project], org: i64) -> Vec<Row> {
+fn create_list(data: &[Project], org: i64, tz: Tz) -> Vec<Row> {
     let mut res = Vec::new();
     debug!("Listing org:{}, data:{:?}", org, data);
     for ent in data {
@@ -28,7 +30,7 @@ fn create_list(data: &[Project], org: i64) -> Vec<Row> {
         cells.push(title_cell);
 
         if let Some(startdate) = ent.start_date {
-            let start_date_cont = CellContent::new(startdate.to_string());
+            let start_date_cont = CellContent::new(timezone::format_date(&startdate, tz));
             let start_date_cell = Cell {
                 title: "Start Date".to_string(),
                 content: start_date_cont,
@@ -37,7 +39,7 @@ fn create_list(data: &[Project], org: i64) -> Vec<Row> {
             cells.push(start_date_cell);
         }
         if let Some(enddate) = ent.end_date {
-            let end_date_cont = CellContent::new(enddate.to_string());
+            let end_date_cont = CellContent::new(timezone::format_date(&enddate, tz));
             let end_date_cell = Cell {
                 title: "End Date".to_string(),
                 content: end_date_cont,
@@ -55,20 +57,22 @@ fn create_list(data: &[Project], org: i64) -> Vec<Row> {
             children: None,
         };
         let links = vec![ed];
-        let row = Row { cells, links };
+        let row = Row {
+            cells,
+            links,
+            permission: None,
+            confirm: None,
+        };
         res.push(row);
     }
     res
 }
 pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
-    // TODO remove when not needed
-    let _res = req.session().set("org", 1);
-    if let Ok(orgids) = std::env::var("todo_ORG") {
-        let orgid = orgids
-            .parse::<i64>()
-            .unwrap_or_else(|_| panic!("{} must be int", "todo_ORG"));
-        let _res = req.session().set("org", orgid);
-        let query = projects.filter(team_id.eq(orgid));
+    if let Some(orgid) = crate::modules::team::current(req)? {
+        let query = projects
+            .filter(team_id.eq(orgid))
+            .filter(deleted_at.is_null())
+            .filter(is_template.eq(false));
         let select = SQuery {
             select: query,
             phantom: PhantomData::<Project>,
@@ -81,22 +85,26 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
             .wait()
         {
             if let Ok(data) = thing {
-                let list = create_list(&data, orgid);
-                return http_ok(index_render(list));
+                let list = create_list(&data, orgid, timezone::current(req));
+                return http_ok(index_render(list, &req.impersonating()));
             }
         }
     } else {
-        return Ok(HttpResponse::SeeOther().body("todo service can not determine the team to use!"));
+        let org_select = "/team/select".to_owned();
+        return Ok(HttpResponse::Found()
+            .header("location", org_select)
+            .finish());
     }
     Ok(HttpResponse::Ok().finish())
 }
-fn index_render(list: Vec<Row>) -> Result<String, Failure> {
+fn index_render(list: Vec<Row>, impersonating: &Option<String>) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let links = Vec::new();
     let ctx = ListContext {
         title: "Project".to_string(),
         head: "List of projects".to_string(),
         search: false,
+        sort: None,
     };
     let perm = crate::modules::navigation::PermissionSet {
         browse: true,
@@ -105,13 +113,20 @@ fn index_render(list: Vec<Row>) -> Result<String, Failure> {
         add: true,
         delete: true,
     };
-    let list = ructe_block_res!(crate::templates::navigation::table, &list, &ctx, &perm)?;
+    let list = ructe_block_res!(
+        crate::templates::navigation::table,
+        &list,
+        &ctx,
+        &perm,
+        &None
+    )?;
     let meta = default_meta("List of Project");
     ructe_page_res!(
         crate::templates::navigation::frame,
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
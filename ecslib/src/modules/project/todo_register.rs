@@ -1,18 +1,31 @@
-use actix_web::middleware::session::RequestSession;
 use actix_web::{Error, Form, FromRequest, HttpRequest, HttpResponse, Path};
 
+use ::uuid::Uuid;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use futures::future::Future;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 
-use crate::db::{AppState, Conn, DQuery, WQuery};
+use crate::db::{AppState, WQuery};
 use crate::modules::navigation::Link;
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
 use crate::render::Failure;
-use crate::utils::http_ok;
+use crate::utils::{http_ok, write_conn};
 
 use crate::modules::meta::default_meta;
+use crate::modules::project::checklist::{self, ChecklistMode, TodoItem};
+use crate::modules::project::comments::{self, ActivityEntry};
 use crate::modules::project::data::Project;
-use crate::modules::project::todo::Todo;
+use crate::modules::project::history::{self, EventKind};
+use crate::modules::project::live::{self, TodoEvent};
+use crate::modules::project::order;
+use crate::modules::project::todo::{Priority, Todo};
+use crate::modules::team::role::{self, Member};
 use crate::schema::todos::dsl::*;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
@@ -22,8 +35,111 @@ pub struct Register {
     pub phone: Option<String>,
     pub description: Option<String>,
     pub completed: Option<String>,
+    /// The user id of the assignee, empty when unassigned
+    #[serde(default)]
+    pub assignee: Option<String>,
+    /// Read in the timezone of the user, see `timezone::parse_input`
+    #[serde(default)]
+    pub due_at: Option<String>,
+    /// One of the names of `Priority`, normal when missing
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// One of the names of `ChecklistMode`, derive when missing
+    #[serde(default)]
+    pub checklist_mode: Option<String>,
 }
-pub fn save((req, form): (HttpRequest<AppState>, Form<Register>)) -> HttpResponse {
+impl Register {
+    /// The priority selected in the form, normal when missing or unknown
+    pub fn chosen_priority(&self) -> Priority {
+        self.priority
+            .as_ref()
+            .and_then(|name| Priority::parse(name.trim()))
+            .unwrap_or_default()
+    }
+    /// The checklist mode selected in the form, derive when missing or unknown
+    pub fn chosen_checklist_mode(&self) -> ChecklistMode {
+        self.checklist_mode
+            .as_ref()
+            .and_then(|name| ChecklistMode::parse(name.trim()))
+            .unwrap_or_default()
+    }
+}
+
+/// The fields of the form which are checked before saving
+struct Checked {
+    assigned: Option<i64>,
+    due: Option<DateTime<Utc>>,
+    priority: Priority,
+    checklist_mode: ChecklistMode,
+}
+
+/// The assignee chosen in the form, `Err` unless it is a member of the team
+fn assignee_of(
+    req: &HttpRequest<AppState>,
+    org_id: i64,
+    form: &Register,
+) -> Result<Option<i64>, ()> {
+    match form.assignee.as_ref().map(|usr| usr.trim()) {
+        None | Some("") => Ok(None),
+        Some(usr) => match usr.parse::<i64>() {
+            Ok(usr_id) if role::role_of(req, org_id, usr_id).is_some() => Ok(Some(usr_id)),
+            _ => Err(()),
+        },
+    }
+}
+
+/// Checks the form, the errors are keyed by the name of the field
+fn check(
+    req: &HttpRequest<AppState>,
+    org_id: i64,
+    form: &Register,
+    tz: Tz,
+) -> Result<Checked, HashMap<String, String>> {
+    let mut errors = HashMap::new();
+    let assigned = assignee_of(req, org_id, form).unwrap_or_else(|_| {
+        errors.insert(
+            "assignee".to_owned(),
+            "Only members of the team can be assigned".to_owned(),
+        );
+        None
+    });
+    let due = match timezone::parse_input(form.due_at.as_ref().map_or("", String::as_str), tz) {
+        Ok(due) => due,
+        Err(e) => {
+            errors.insert("due_at".to_owned(), e);
+            None
+        }
+    };
+    let priority = match form.priority.as_ref().map(|p| p.trim()) {
+        None | Some("") => Priority::default(),
+        Some(name) => Priority::parse(name).unwrap_or_else(|| {
+            errors.insert("priority".to_owned(), format!("Unknown priority {}", name));
+            Priority::default()
+        }),
+    };
+    let checklist_mode = match form.checklist_mode.as_ref().map(|m| m.trim()) {
+        None | Some("") => ChecklistMode::default(),
+        Some(name) => ChecklistMode::parse(name).unwrap_or_else(|| {
+            errors.insert(
+                "checklist_mode".to_owned(),
+                format!("Unknown checklist mode {}", name),
+            );
+            ChecklistMode::default()
+        }),
+    };
+    if errors.is_empty() {
+        Ok(Checked {
+            assigned,
+            due,
+            priority,
+            checklist_mode,
+        })
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn save((req, form): (HttpRequest<AppState>, Form<Register>)) -> Result<HttpResponse, Error> {
     debug!(
         "todo from: {:?} data:{:?}",
         req.connection_info().remote(),
@@ -32,15 +148,35 @@ pub fn save((req, form): (HttpRequest<AppState>, Form<Register>)) -> HttpRespons
     // let params = Path::<(String, String)>::extract(&req).unwrap();
     // let ecs = &params.1;
     if req.match_info().get("id").is_none() {
-        return HttpResponse::BadRequest().finish();
+        return Ok(HttpResponse::BadRequest().finish());
     }
     let ecs = req.match_info().get("id").unwrap();
     debug!("ecs str: {}", ecs);
     let ecs = ecs.parse::<i64>().unwrap();
     debug!("ecs id: {}", ecs);
-    if let Some(orgid) = req.session().get::<i64>("org").unwrap() {
+    if let Ok(Some(orgid)) = crate::modules::team::writable(&req) {
+        if !crate::modules::team::permission(&req, orgid).add {
+            return Ok(HttpResponse::Forbidden().finish());
+        }
         debug!("{},{}", orgid, ecs);
         if let Ok(project) = Project::load(&req, orgid, ecs) {
+            let checked = match check(&req, orgid, &form, timezone::current(&req)) {
+                Ok(checked) => checked,
+                Err(errors) => {
+                    let members = role::members(&req, orgid).unwrap_or_default();
+                    return http_ok(index_render(
+                        &project,
+                        &form,
+                        &members,
+                        &[],
+                        false,
+                        None,
+                        &[],
+                        &errors,
+                        &req.impersonating(),
+                    ));
+                }
+            };
             let project_uuid = project.uuid;
             let email_ = if let Some(email_) = &form.email {
                 Some(email_.trim().to_owned())
@@ -65,42 +201,68 @@ pub fn save((req, form): (HttpRequest<AppState>, Form<Register>)) -> HttpRespons
                 phone: phone_,
                 description: desc,
                 completed: form.completed.clone(),
+                assignee: form.assignee.clone(),
+                due_at: form.due_at.clone(),
+                priority: form.priority.clone(),
+                checklist_mode: form.checklist_mode.clone(),
             };
             let present = match form.completed {
                 Some(thing) => thing.eq("on"),
                 None => false,
             };
-            let query = diesel::insert_into(todos).values((
-                title.eq(form.title),
-                email.eq(form.email),
-                phone.eq(form.phone),
-                description.eq(form.description),
-                project_id.eq(project_uuid),
-                completed.eq(present),
-            ));
-            let ins = WQuery {
-                query,
-                phantom: PhantomData::<Todo>,
-            };
-            let res = req
-                .state()
-                .wdb
-                .send(ins)
-                .map_err(actix_web::Error::from)
-                .wait()
+            let usr_id = UserMeta::current(&req)
                 .ok()
-                .unwrap()
-                .unwrap();
+                .map(|usr_meta| usr_meta.user_id);
+            let conn = write_conn(&req)?;
+            let res = conn.transaction::<_, diesel::result::Error, _>(|| {
+                let last = order::append_positions(&conn, project_uuid, 1)?;
+                let todo: Todo = diesel::insert_into(todos)
+                    .values((
+                        title.eq(form.title),
+                        email.eq(form.email),
+                        phone.eq(form.phone),
+                        description.eq(form.description),
+                        project_id.eq(project_uuid),
+                        completed.eq(present),
+                        completed_at.eq(if present { Some(Utc::now()) } else { None }),
+                        completed_by.eq(if present { usr_id } else { None }),
+                        assignee_id.eq(checked.assigned),
+                        due_at.eq(checked.due),
+                        priority.eq(checked.priority.value()),
+                        position.eq(last[0]),
+                        checklist_mode.eq(checked.checklist_mode.as_str()),
+                    ))
+                    .get_result(&conn)?;
+                history::record(&conn, &[todo.id], EventKind::Created, usr_id)?;
+                if present {
+                    history::record(&conn, &[todo.id], EventKind::Completed, usr_id)?;
+                }
+                Ok(todo)
+            });
             debug!("{:?}", res);
+            if let Ok(todo) = res {
+                live::publish(TodoEvent::added(&todo));
+            }
             // Ok(HttpResponse::Found().header("location", "../invalid").finish())
         }
     }
-    HttpResponse::Found().header("location", "./todo").finish()
+    Ok(HttpResponse::Found().header("location", "./todo").finish())
     // HttpResponse::Found().body("aaa")
     // http_ok(Ok("ssss".to_string()))
 }
 
-fn index_render(project: &Project, reg_data: &Register) -> Result<String, Failure> {
+#[allow(clippy::too_many_arguments)]
+fn index_render(
+    project: &Project,
+    reg_data: &Register,
+    members: &[Member],
+    activity: &[ActivityEntry],
+    can_comment: bool,
+    todo_id: Option<i64>,
+    items: &[TodoItem],
+    errors: &HashMap<String, String>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
     // let toplinks = crate::menu::default_top_menu();
     let toplinks = Vec::new();
     let links = vec![
@@ -110,14 +272,27 @@ fn index_render(project: &Project, reg_data: &Register) -> Result<String, Failur
         ),
         Link::new("Todo list", &format!("/project/{}/todo", project.projectid)),
     ];
-    let list = ructe_block_res!(crate::templates::project::todo_register, project, reg_data)?;
+    let list = ructe_block_res!(
+        crate::templates::project::todo_register,
+        project,
+        reg_data,
+        members,
+        activity,
+        can_comment,
+        todo_id,
+        items,
+        &Priority::all(),
+        &ChecklistMode::all(),
+        errors
+    )?;
     let meta = default_meta("project registration");
     ructe_page_res!(
         crate::templates::navigation::frame,
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
 
@@ -126,13 +301,24 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
         .unwrap()
         .parse::<i64>()
         .unwrap();
-    if let Some(orgid) = req.session().get::<i64>("org")? {
+    if let Some(orgid) = crate::modules::team::current(req)? {
         debug!("{},{}", orgid, ecs);
         if let Ok(project) = Project::load(&req, orgid, ecs) {
-            return http_ok(index_render(&project, &Register::default()));
+            let members = role::members(req, orgid).unwrap_or_default();
+            return http_ok(index_render(
+                &project,
+                &Register::default(),
+                &members,
+                &[],
+                false,
+                None,
+                &[],
+                &HashMap::new(),
+                &req.impersonating(),
+            ));
         }
     }
-    let org_select = "/org/select".to_owned();
+    let org_select = "/team/select".to_owned();
     Ok(HttpResponse::Found()
         .header("location", org_select)
         .finish())
@@ -148,22 +334,40 @@ pub fn edit_page(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
     }
     let aid = req.match_info().get("aid").unwrap().parse::<i64>().unwrap();
 
-    if let Some(orgid) = req.session().get::<i64>("org")? {
+    if let Some(orgid) = crate::modules::team::current(req)? {
         debug!("{},{}", orgid, ecs);
         if let Ok(project) = Project::load(&req, orgid, ecs) {
-            if let Ok(att) = Todo::load(&req, aid) {
+            if let Ok(att) = Todo::load(&req, project.uuid, aid) {
+                let tz = timezone::current(req);
                 let reg = Register {
                     title: att.title,
                     email: att.email,
                     phone: att.phone,
                     description: att.description,
                     completed: None,
+                    assignee: att.assignee_id.map(|usr_id| usr_id.to_string()),
+                    due_at: att.due_at.map(|due| timezone::format_input(&due, tz)),
+                    priority: Some(att.priority().as_str().to_owned()),
+                    checklist_mode: Some(att.checklist_mode().as_str().to_owned()),
                 };
-                return http_ok(index_render(&project, &reg));
+                let members = role::members(req, orgid).unwrap_or_default();
+                let (activity, can_comment) = comments::feed(req, &project, Some(aid), tz);
+                let items = checklist::of_todo(req, aid).unwrap_or_default();
+                return http_ok(index_render(
+                    &project,
+                    &reg,
+                    &members,
+                    &activity,
+                    can_comment,
+                    Some(aid),
+                    &items,
+                    &HashMap::new(),
+                    &req.impersonating(),
+                ));
             }
         }
     }
-    let org_select = "/org/select".to_owned();
+    let org_select = "/team/select".to_owned();
     Ok(HttpResponse::Found()
         .header("location", org_select)
         .finish())
@@ -180,9 +384,31 @@ pub fn save_todo(
         return Ok(HttpResponse::BadRequest().finish());
     }
     let aid = req.match_info().get("aid").unwrap().parse::<i64>().unwrap();
-    if let Some(orgid) = req.session().get::<i64>("org")? {
-        if let Ok(_project) = Project::load(&req, orgid, ecs) {
-            // if project uuid would be in a hidden, we could double check here
+    if let Some(orgid) = crate::modules::team::writable(&req)? {
+        if !crate::modules::team::permission(&req, orgid).edit {
+            return Ok(HttpResponse::Forbidden().finish());
+        }
+        if let Ok(project) = Project::load(&req, orgid, ecs) {
+            let tz = timezone::current(&req);
+            let checked = match check(&req, orgid, &form, tz) {
+                Ok(checked) => checked,
+                Err(errors) => {
+                    let members = role::members(&req, orgid).unwrap_or_default();
+                    let (activity, can_comment) = comments::feed(&req, &project, Some(aid), tz);
+                    let items = checklist::of_todo(&req, aid).unwrap_or_default();
+                    return http_ok(index_render(
+                        &project,
+                        &form,
+                        &members,
+                        &activity,
+                        can_comment,
+                        Some(aid),
+                        &items,
+                        &errors,
+                        &req.impersonating(),
+                    ));
+                }
+            };
             let email_ = if let Some(email_) = &form.email {
                 Some(email_.trim().to_owned())
             } else {
@@ -206,14 +432,25 @@ pub fn save_todo(
                 phone: phone_,
                 description: desc,
                 completed: form.completed.clone(),
+                assignee: form.assignee.clone(),
+                due_at: form.due_at.clone(),
+                priority: form.priority.clone(),
+                checklist_mode: form.checklist_mode.clone(),
             };
 
-            let target = todos.filter(id.eq(aid));
+            let target = todos
+                .filter(id.eq(aid))
+                .filter(project_id.eq(project.uuid))
+                .filter(deleted_at.is_null());
             let query = diesel::update(target).set((
                 title.eq(form.title),
                 email.eq(form.email),
                 phone.eq(form.phone),
                 description.eq(form.description),
+                assignee_id.eq(checked.assigned),
+                due_at.eq(checked.due),
+                priority.eq(checked.priority.value()),
+                checklist_mode.eq(checked.checklist_mode.as_str()),
             ));
             let upd = WQuery {
                 query,
@@ -221,6 +458,24 @@ pub fn save_todo(
             };
             let res = req.state().wdb.send(upd).wait().ok().unwrap();
             debug!("{:?}", res);
+            if let Ok(updated) = &res {
+                for todo in updated.iter() {
+                    live::publish(TodoEvent::edited(todo));
+                }
+            }
+            if res.as_ref().map_or(false, |updated| !updated.is_empty()) {
+                // the checklist mode may have changed what the completion should be
+                let usr_id = UserMeta::current(&req)
+                    .ok()
+                    .map(|usr_meta| usr_meta.user_id);
+                let conn = write_conn(&req)?;
+                let synced = conn.transaction::<_, diesel::result::Error, _>(|| {
+                    checklist::sync_completion(&conn, aid, usr_id)
+                });
+                if let Ok(Some(parent)) = synced {
+                    live::publish(TodoEvent::toggled(&parent));
+                }
+            }
             let route = "../todo";
             return Ok(HttpResponse::Found().header("location", route).finish());
         }
@@ -228,6 +483,29 @@ pub fn save_todo(
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Moves the todo to the trash with `deleted_at`, its history and comments stay and the
+/// deletion is recorded like any other change
+fn soft_delete(
+    conn: &PgConnection,
+    project_uuid: Uuid,
+    aid: i64,
+    usr_id: Option<i64>,
+) -> QueryResult<usize> {
+    conn.transaction(|| {
+        let target = todos
+            .filter(id.eq(aid))
+            .filter(project_id.eq(project_uuid))
+            .filter(deleted_at.is_null());
+        let deleted = diesel::update(target)
+            .set(deleted_at.eq(Some(Utc::now())))
+            .execute(conn)?;
+        if deleted > 0 {
+            history::record(conn, &[aid], EventKind::Deleted, usr_id)?;
+        }
+        Ok(deleted)
+    })
+}
+
 pub fn delete_todo_conn(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
     if req.match_info().get("id").is_none() {
         return Ok(HttpResponse::BadRequest().finish());
@@ -237,11 +515,18 @@ pub fn delete_todo_conn(req: &HttpRequest<AppState>) -> Result<HttpResponse, Err
         return Ok(HttpResponse::BadRequest().finish());
     }
     let aid = req.match_info().get("aid").unwrap().parse::<i64>().unwrap();
-    if let Some(orgid) = req.session().get::<i64>("org")? {
+    if let Some(orgid) = crate::modules::team::writable(req)? {
+        if !crate::modules::team::permission(req, orgid).delete {
+            return Ok(HttpResponse::Forbidden().finish());
+        }
         if let Ok(project) = Project::load(&req, orgid, ecs) {
-            let conn = req.state().wdb.send(Conn {}).wait().ok().unwrap().unwrap();
-            let res = diesel::delete(todos.filter(id.eq(aid))).execute(&conn);
+            let usr_id = UserMeta::current(req).ok().map(|usr_meta| usr_meta.user_id);
+            let conn = write_conn(req)?;
+            let res = soft_delete(&conn, project.uuid, aid, usr_id);
             debug!("{:?}", res);
+            if let Ok(1) = res {
+                live::publish(TodoEvent::deleted(project.uuid, aid));
+            }
             let route = format!("/project/{}/todo", project.projectid);
             return Ok(HttpResponse::Found().header("location", route).finish());
         }
@@ -258,12 +543,18 @@ pub fn delete_todo(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
         return Ok(HttpResponse::BadRequest().finish());
     }
     let aid = req.match_info().get("aid").unwrap().parse::<i64>().unwrap();
-    if let Some(orgid) = req.session().get::<i64>("org")? {
+    if let Some(orgid) = crate::modules::team::writable(req)? {
+        if !crate::modules::team::permission(req, orgid).delete {
+            return Ok(HttpResponse::Forbidden().finish());
+        }
         if let Ok(project) = Project::load(&req, orgid, ecs) {
-            let query = diesel::delete(todos.filter(id.eq(aid)));
-            let del = DQuery { query };
-            let res = req.state().wdb.send(del).wait().ok().unwrap();
+            let usr_id = UserMeta::current(req).ok().map(|usr_meta| usr_meta.user_id);
+            let conn = write_conn(req)?;
+            let res = soft_delete(&conn, project.uuid, aid, usr_id);
             debug!("{:?}", res);
+            if let Ok(1) = res {
+                live::publish(TodoEvent::deleted(project.uuid, aid));
+            }
             let route = format!("/project/{}/todo", project.projectid);
             return Ok(HttpResponse::Found().header("location", route).finish());
         }
@@ -0,0 +1,136 @@
+use actix_web::{Error, HttpRequest, HttpResponse};
+
+use chrono_tz::Tz;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text, Uuid as SqlUuid};
+use futures::future::Future;
+
+use crate::db::{AppState, Conn};
+use crate::modules::access::allowed;
+use crate::modules::meta::default_meta;
+use crate::modules::navigation::{Link, PermissionSet};
+use crate::modules::timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::render::Failure;
+use crate::utils::http_ok;
+
+use super::comments::{self, ActivityEntry};
+use super::data::{Project, ProjectStatus, TodoSummary};
+
+/// The todo counts and the status of a project, the rules of the status are in the view
+const SUMMARY_SQL: &str = r#"
+SELECT total_todos, done_todos, status FROM project_statuses WHERE project_id = $1
+"#;
+
+#[derive(QueryableByName, Debug, Clone)]
+struct SummaryRow {
+    #[sql_type = "BigInt"]
+    total_todos: i64,
+    #[sql_type = "BigInt"]
+    done_todos: i64,
+    #[sql_type = "Text"]
+    status: String,
+}
+
+fn summary(conn: &PgConnection, project: &Project) -> QueryResult<(TodoSummary, ProjectStatus)> {
+    let row: SummaryRow = diesel::sql_query(SUMMARY_SQL)
+        .bind::<SqlUuid, _>(project.uuid)
+        .get_result(conn)?;
+    let todo_summary = TodoSummary {
+        total: row.total_todos,
+        completed: row.done_todos,
+    };
+    let status = ProjectStatus::parse(&row.status).unwrap_or(ProjectStatus::Active);
+    Ok((todo_summary, status))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn index_render(
+    project: &Project,
+    status: ProjectStatus,
+    todo_summary: &TodoSummary,
+    perm: &PermissionSet,
+    tz: Tz,
+    feed: &[ActivityEntry],
+    can_comment: bool,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = vec![
+        Link::new("Todo list", &format!("/project/{}/todo", project.projectid)),
+        Link::new(
+            "Add todo",
+            &format!("/project/{}/register", project.projectid),
+        ),
+        Link::new("Edit", &format!("/project/{}/edit", project.projectid)),
+    ];
+    let cnt = ructe_block_res!(
+        crate::templates::project::view,
+        project,
+        status,
+        todo_summary,
+        perm,
+        tz,
+        feed,
+        can_comment
+    )?;
+    let meta = default_meta(&project.title);
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let pid = match req.match_info().get("id").map(str::parse::<i64>) {
+        Some(Ok(pid)) => pid,
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let org = match crate::modules::team::current(req)? {
+        Some(orgid) => match crate::modules::team::load(req, orgid) {
+            Ok(org) => org,
+            Err(_) => return Ok(HttpResponse::NotFound().finish()),
+        },
+        None => {
+            return Ok(HttpResponse::Found()
+                .header("location", "/team/select")
+                .finish())
+        }
+    };
+    let perm = allowed(req, org.access_control_id);
+    if !perm.read {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let project = match Project::load(req, org.id, pid) {
+        Ok(project) => project,
+        Err(_) => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let conn = match req.state().rdb.send(Conn {}).wait() {
+        Ok(Ok(conn)) => conn,
+        _ => return Ok(HttpResponse::ServiceUnavailable().finish()),
+    };
+    let (todo_summary, status) = match summary(&conn, &project) {
+        Ok(found) => found,
+        Err(e) => {
+            error!("Summary of project {} failed: {:?}", project.projectid, e);
+            (TodoSummary::default(), ProjectStatus::Active)
+        }
+    };
+    let tz = timezone::current(req);
+    let (feed, can_comment) = comments::feed(req, &project, None, tz);
+    http_ok(index_render(
+        &project,
+        status,
+        &todo_summary,
+        &perm,
+        tz,
+        &feed,
+        can_comment,
+        &req.impersonating(),
+    ))
+}
@@ -0,0 +1,1934 @@
+/// An ISO-3166 country with the formats of its postal codes and VAT ids.
+///
+/// Formats are masks: `9` is a digit, `A` is a letter, `X` is either, everything else
+/// stands for itself. A backslash makes the character after it stand for itself, as the
+/// `A` of the Andorran `\AD999`. A country without postal masks accepts any postal code.
+#[derive(Debug, Clone, Serialize)]
+pub struct Country {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub postal: &'static [&'static str],
+    /// The prefix of VAT ids in the VIES system, only EU member states have one
+    pub vat_prefix: Option<&'static str>,
+    pub vat: &'static [&'static str],
+}
+
+impl Country {
+    pub fn find(code: &str) -> Option<&'static Country> {
+        COUNTRIES.iter().find(|cnt| cnt.code == code)
+    }
+    pub fn valid_postal(&self, zip: &str) -> bool {
+        self.postal.is_empty() || self.postal.iter().any(|mask| matches_mask(zip, mask))
+    }
+    /// The VAT id has to carry the prefix of the country, spaces are ignored
+    pub fn valid_vat(&self, vat: &str) -> bool {
+        let vat: String = vat
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase();
+        match self.vat_prefix {
+            Some(pfx) if vat.starts_with(pfx) => self
+                .vat
+                .iter()
+                .any(|mask| matches_mask(&vat[pfx.len()..], mask)),
+            _ => false,
+        }
+    }
+    /// Examples of the accepted postal codes, for error messages
+    pub fn postal_hint(&self) -> String {
+        let masks: Vec<String> = self
+            .postal
+            .iter()
+            .map(|mask| mask.replace('\\', ""))
+            .collect();
+        masks.join(", ")
+    }
+}
+
+pub fn matches_mask(value: &str, mask: &str) -> bool {
+    let mut value = value.chars();
+    let mut mask = mask.chars();
+    while let Some(m) = mask.next() {
+        let v = match value.next() {
+            Some(v) => v,
+            None => return false,
+        };
+        let matched = match m {
+            '\\' => mask
+                .next()
+                .map_or(false, |lit| v.eq_ignore_ascii_case(&lit)),
+            '9' => v.is_ascii_digit(),
+            'A' => v.is_ascii_alphabetic(),
+            'X' => v.is_ascii_alphanumeric(),
+            _ => v.eq_ignore_ascii_case(&m),
+        };
+        if !matched {
+            return false;
+        }
+    }
+    value.next().is_none()
+}
+
+pub const COUNTRIES: &[Country] = &[
+    Country {
+        code: "AD",
+        name: "Andorra",
+        postal: &["\\AD999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AE",
+        name: "United Arab Emirates",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AF",
+        name: "Afghanistan",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AG",
+        name: "Antigua and Barbuda",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AI",
+        name: "Anguilla",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AL",
+        name: "Albania",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AM",
+        name: "Armenia",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AO",
+        name: "Angola",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AQ",
+        name: "Antarctica",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AR",
+        name: "Argentina",
+        postal: &["A9999AAA", "9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AS",
+        name: "American Samoa",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AT",
+        name: "Austria",
+        postal: &["9999"],
+        vat_prefix: Some("AT"),
+        vat: &["U99999999"],
+    },
+    Country {
+        code: "AU",
+        name: "Australia",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AW",
+        name: "Aruba",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AX",
+        name: "Åland Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "AZ",
+        name: "Azerbaijan",
+        postal: &["\\AZ 9999", "\\AZ9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BA",
+        name: "Bosnia and Herzegovina",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BB",
+        name: "Barbados",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BD",
+        name: "Bangladesh",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BE",
+        name: "Belgium",
+        postal: &["9999"],
+        vat_prefix: Some("BE"),
+        vat: &["9999999999"],
+    },
+    Country {
+        code: "BF",
+        name: "Burkina Faso",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BG",
+        name: "Bulgaria",
+        postal: &["9999"],
+        vat_prefix: Some("BG"),
+        vat: &["999999999", "9999999999"],
+    },
+    Country {
+        code: "BH",
+        name: "Bahrain",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BI",
+        name: "Burundi",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BJ",
+        name: "Benin",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BL",
+        name: "Saint Barthélemy",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BM",
+        name: "Bermuda",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BN",
+        name: "Brunei Darussalam",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BO",
+        name: "Bolivia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BQ",
+        name: "Bonaire, Sint Eustatius and Saba",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BR",
+        name: "Brazil",
+        postal: &["99999-999", "99999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BS",
+        name: "Bahamas",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BT",
+        name: "Bhutan",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BV",
+        name: "Bouvet Island",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BW",
+        name: "Botswana",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BY",
+        name: "Belarus",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "BZ",
+        name: "Belize",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CA",
+        name: "Canada",
+        postal: &["A9A 9A9", "A9A9A9"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CC",
+        name: "Cocos (Keeling) Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CD",
+        name: "Congo, The Democratic Republic of the",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CF",
+        name: "Central African Republic",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CG",
+        name: "Congo",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CH",
+        name: "Switzerland",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CI",
+        name: "Côte d'Ivoire",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CK",
+        name: "Cook Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CL",
+        name: "Chile",
+        postal: &["9999999", "999-9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CM",
+        name: "Cameroon",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CN",
+        name: "China",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CO",
+        name: "Colombia",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CR",
+        name: "Costa Rica",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CU",
+        name: "Cuba",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CV",
+        name: "Cabo Verde",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CW",
+        name: "Curaçao",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CX",
+        name: "Christmas Island",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "CY",
+        name: "Cyprus",
+        postal: &["9999"],
+        vat_prefix: Some("CY"),
+        vat: &["99999999A"],
+    },
+    Country {
+        code: "CZ",
+        name: "Czechia",
+        postal: &["999 99", "99999"],
+        vat_prefix: Some("CZ"),
+        vat: &["99999999", "999999999", "9999999999"],
+    },
+    Country {
+        code: "DE",
+        name: "Germany",
+        postal: &["99999"],
+        vat_prefix: Some("DE"),
+        vat: &["999999999"],
+    },
+    Country {
+        code: "DJ",
+        name: "Djibouti",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "DK",
+        name: "Denmark",
+        postal: &["9999"],
+        vat_prefix: Some("DK"),
+        vat: &["99999999"],
+    },
+    Country {
+        code: "DM",
+        name: "Dominica",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "DO",
+        name: "Dominican Republic",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "DZ",
+        name: "Algeria",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "EC",
+        name: "Ecuador",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "EE",
+        name: "Estonia",
+        postal: &["99999"],
+        vat_prefix: Some("EE"),
+        vat: &["999999999"],
+    },
+    Country {
+        code: "EG",
+        name: "Egypt",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "EH",
+        name: "Western Sahara",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ER",
+        name: "Eritrea",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ES",
+        name: "Spain",
+        postal: &["99999"],
+        vat_prefix: Some("ES"),
+        vat: &["X9999999X"],
+    },
+    Country {
+        code: "ET",
+        name: "Ethiopia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "FI",
+        name: "Finland",
+        postal: &["99999"],
+        vat_prefix: Some("FI"),
+        vat: &["99999999"],
+    },
+    Country {
+        code: "FJ",
+        name: "Fiji",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "FK",
+        name: "Falkland Islands (Malvinas)",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "FM",
+        name: "Micronesia, Federated States of",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "FO",
+        name: "Faroe Islands",
+        postal: &["999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "FR",
+        name: "France",
+        postal: &["99999"],
+        vat_prefix: Some("FR"),
+        vat: &["XX999999999"],
+    },
+    Country {
+        code: "GA",
+        name: "Gabon",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GB",
+        name: "United Kingdom",
+        postal: &[
+            "A9 9AA", "A99 9AA", "AA9 9AA", "AA99 9AA", "A9A 9AA", "AA9A 9AA",
+        ],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GD",
+        name: "Grenada",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GE",
+        name: "Georgia",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GF",
+        name: "French Guiana",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GG",
+        name: "Guernsey",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GH",
+        name: "Ghana",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GI",
+        name: "Gibraltar",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GL",
+        name: "Greenland",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GM",
+        name: "Gambia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GN",
+        name: "Guinea",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GP",
+        name: "Guadeloupe",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GQ",
+        name: "Equatorial Guinea",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GR",
+        name: "Greece",
+        postal: &["999 99", "99999"],
+        vat_prefix: Some("EL"),
+        vat: &["999999999"],
+    },
+    Country {
+        code: "GS",
+        name: "South Georgia and the South Sandwich Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GT",
+        name: "Guatemala",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GU",
+        name: "Guam",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GW",
+        name: "Guinea-Bissau",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "GY",
+        name: "Guyana",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "HK",
+        name: "Hong Kong",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "HM",
+        name: "Heard Island and McDonald Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "HN",
+        name: "Honduras",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "HR",
+        name: "Croatia",
+        postal: &["99999"],
+        vat_prefix: Some("HR"),
+        vat: &["99999999999"],
+    },
+    Country {
+        code: "HT",
+        name: "Haiti",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "HU",
+        name: "Hungary",
+        postal: &["9999"],
+        vat_prefix: Some("HU"),
+        vat: &["99999999"],
+    },
+    Country {
+        code: "ID",
+        name: "Indonesia",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IE",
+        name: "Ireland",
+        postal: &["A99 XXXX"],
+        vat_prefix: Some("IE"),
+        vat: &["9999999A", "9999999AA", "9X99999A"],
+    },
+    Country {
+        code: "IL",
+        name: "Israel",
+        postal: &["9999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IM",
+        name: "Isle of Man",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IN",
+        name: "India",
+        postal: &["999999", "999 999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IO",
+        name: "British Indian Ocean Territory",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IQ",
+        name: "Iraq",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IR",
+        name: "Iran",
+        postal: &["99999-99999", "9999999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IS",
+        name: "Iceland",
+        postal: &["999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "IT",
+        name: "Italy",
+        postal: &["99999"],
+        vat_prefix: Some("IT"),
+        vat: &["99999999999"],
+    },
+    Country {
+        code: "JE",
+        name: "Jersey",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "JM",
+        name: "Jamaica",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "JO",
+        name: "Jordan",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "JP",
+        name: "Japan",
+        postal: &["999-9999", "9999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KE",
+        name: "Kenya",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KG",
+        name: "Kyrgyzstan",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KH",
+        name: "Cambodia",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KI",
+        name: "Kiribati",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KM",
+        name: "Comoros",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KN",
+        name: "Saint Kitts and Nevis",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KP",
+        name: "North Korea",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KR",
+        name: "South Korea",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KW",
+        name: "Kuwait",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KY",
+        name: "Cayman Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "KZ",
+        name: "Kazakhstan",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LA",
+        name: "Laos",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LB",
+        name: "Lebanon",
+        postal: &["9999 9999", "9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LC",
+        name: "Saint Lucia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LI",
+        name: "Liechtenstein",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LK",
+        name: "Sri Lanka",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LR",
+        name: "Liberia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LS",
+        name: "Lesotho",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "LT",
+        name: "Lithuania",
+        postal: &["LT-99999", "99999"],
+        vat_prefix: Some("LT"),
+        vat: &["999999999", "999999999999"],
+    },
+    Country {
+        code: "LU",
+        name: "Luxembourg",
+        postal: &["L-9999", "9999"],
+        vat_prefix: Some("LU"),
+        vat: &["99999999"],
+    },
+    Country {
+        code: "LV",
+        name: "Latvia",
+        postal: &["LV-9999"],
+        vat_prefix: Some("LV"),
+        vat: &["99999999999"],
+    },
+    Country {
+        code: "LY",
+        name: "Libya",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MA",
+        name: "Morocco",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MC",
+        name: "Monaco",
+        postal: &["\\98\\099"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MD",
+        name: "Moldova",
+        postal: &["MD9999", "MD-9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ME",
+        name: "Montenegro",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MF",
+        name: "Saint Martin (French part)",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MG",
+        name: "Madagascar",
+        postal: &["999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MH",
+        name: "Marshall Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MK",
+        name: "North Macedonia",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ML",
+        name: "Mali",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MM",
+        name: "Myanmar",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MN",
+        name: "Mongolia",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MO",
+        name: "Macao",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MP",
+        name: "Northern Mariana Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MQ",
+        name: "Martinique",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MR",
+        name: "Mauritania",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MS",
+        name: "Montserrat",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MT",
+        name: "Malta",
+        postal: &["AAA 9999", "AAA9999"],
+        vat_prefix: Some("MT"),
+        vat: &["99999999"],
+    },
+    Country {
+        code: "MU",
+        name: "Mauritius",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MV",
+        name: "Maldives",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MW",
+        name: "Malawi",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MX",
+        name: "Mexico",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MY",
+        name: "Malaysia",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "MZ",
+        name: "Mozambique",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NA",
+        name: "Namibia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NC",
+        name: "New Caledonia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NE",
+        name: "Niger",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NF",
+        name: "Norfolk Island",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NG",
+        name: "Nigeria",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NI",
+        name: "Nicaragua",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NL",
+        name: "Netherlands",
+        postal: &["9999 AA", "9999AA"],
+        vat_prefix: Some("NL"),
+        vat: &["999999999B99"],
+    },
+    Country {
+        code: "NO",
+        name: "Norway",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NP",
+        name: "Nepal",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NR",
+        name: "Nauru",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NU",
+        name: "Niue",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "NZ",
+        name: "New Zealand",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "OM",
+        name: "Oman",
+        postal: &["999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PA",
+        name: "Panama",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PE",
+        name: "Peru",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PF",
+        name: "French Polynesia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PG",
+        name: "Papua New Guinea",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PH",
+        name: "Philippines",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PK",
+        name: "Pakistan",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PL",
+        name: "Poland",
+        postal: &["99-999"],
+        vat_prefix: Some("PL"),
+        vat: &["9999999999"],
+    },
+    Country {
+        code: "PM",
+        name: "Saint Pierre and Miquelon",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PN",
+        name: "Pitcairn",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PR",
+        name: "Puerto Rico",
+        postal: &["99999", "99999-9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PS",
+        name: "Palestine, State of",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PT",
+        name: "Portugal",
+        postal: &["9999-999"],
+        vat_prefix: Some("PT"),
+        vat: &["999999999"],
+    },
+    Country {
+        code: "PW",
+        name: "Palau",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "PY",
+        name: "Paraguay",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "QA",
+        name: "Qatar",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "RE",
+        name: "Réunion",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "RO",
+        name: "Romania",
+        postal: &["999999"],
+        vat_prefix: Some("RO"),
+        vat: &[
+            "99",
+            "999",
+            "9999",
+            "99999",
+            "999999",
+            "9999999",
+            "99999999",
+            "999999999",
+            "9999999999",
+        ],
+    },
+    Country {
+        code: "RS",
+        name: "Serbia",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "RU",
+        name: "Russian Federation",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "RW",
+        name: "Rwanda",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SA",
+        name: "Saudi Arabia",
+        postal: &["99999", "99999-9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SB",
+        name: "Solomon Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SC",
+        name: "Seychelles",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SD",
+        name: "Sudan",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SE",
+        name: "Sweden",
+        postal: &["999 99", "99999"],
+        vat_prefix: Some("SE"),
+        vat: &["999999999999"],
+    },
+    Country {
+        code: "SG",
+        name: "Singapore",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SH",
+        name: "Saint Helena, Ascension and Tristan da Cunha",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SI",
+        name: "Slovenia",
+        postal: &["9999", "SI-9999"],
+        vat_prefix: Some("SI"),
+        vat: &["99999999"],
+    },
+    Country {
+        code: "SJ",
+        name: "Svalbard and Jan Mayen",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SK",
+        name: "Slovakia",
+        postal: &["999 99", "99999"],
+        vat_prefix: Some("SK"),
+        vat: &["9999999999"],
+    },
+    Country {
+        code: "SL",
+        name: "Sierra Leone",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SM",
+        name: "San Marino",
+        postal: &["478\\99"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SN",
+        name: "Senegal",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SO",
+        name: "Somalia",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SR",
+        name: "Suriname",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SS",
+        name: "South Sudan",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ST",
+        name: "Sao Tome and Principe",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SV",
+        name: "El Salvador",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SX",
+        name: "Sint Maarten (Dutch part)",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SY",
+        name: "Syria",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "SZ",
+        name: "Eswatini",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TC",
+        name: "Turks and Caicos Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TD",
+        name: "Chad",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TF",
+        name: "French Southern Territories",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TG",
+        name: "Togo",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TH",
+        name: "Thailand",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TJ",
+        name: "Tajikistan",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TK",
+        name: "Tokelau",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TL",
+        name: "Timor-Leste",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TM",
+        name: "Turkmenistan",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TN",
+        name: "Tunisia",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TO",
+        name: "Tonga",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TR",
+        name: "Türkiye",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TT",
+        name: "Trinidad and Tobago",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TV",
+        name: "Tuvalu",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TW",
+        name: "Taiwan",
+        postal: &["999", "99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "TZ",
+        name: "Tanzania",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "UA",
+        name: "Ukraine",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "UG",
+        name: "Uganda",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "UM",
+        name: "United States Minor Outlying Islands",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "US",
+        name: "United States",
+        postal: &["99999", "99999-9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "UY",
+        name: "Uruguay",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "UZ",
+        name: "Uzbekistan",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "VA",
+        name: "Holy See (Vatican City State)",
+        postal: &["00120"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "VC",
+        name: "Saint Vincent and the Grenadines",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "VE",
+        name: "Venezuela",
+        postal: &["9999", "9999-A"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "VG",
+        name: "Virgin Islands, British",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "VI",
+        name: "Virgin Islands, U.S.",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "VN",
+        name: "Vietnam",
+        postal: &["999999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "VU",
+        name: "Vanuatu",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "WF",
+        name: "Wallis and Futuna",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "WS",
+        name: "Samoa",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "YE",
+        name: "Yemen",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "YT",
+        name: "Mayotte",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ZA",
+        name: "South Africa",
+        postal: &["9999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ZM",
+        name: "Zambia",
+        postal: &["99999"],
+        vat_prefix: None,
+        vat: &[],
+    },
+    Country {
+        code: "ZW",
+        name: "Zimbabwe",
+        postal: &[],
+        vat_prefix: None,
+        vat: &[],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_mask, Country};
+
+    #[test]
+    fn masks() {
+        let cases = [
+            ("1234", "9999", true),
+            ("123", "9999", false),
+            ("12a4", "9999", false),
+            ("SW1A 1AA", "AA9A 9AA", true),
+            ("sw1a 1aa", "AA9A 9AA", true),
+            ("SW1A1AA", "AA9A 9AA", false),
+            ("D02 X285", "A99 XXXX", true),
+            ("AD100", "\\AD999", true),
+            ("ad100", "\\AD999", true),
+            ("AE100", "\\AD999", false),
+            // escaped letters are not wildcards
+            ("XD100", "\\AD999", false),
+            ("XD100", "AD999", true),
+            ("98012", "\\98\\099", true),
+            ("18012", "\\98\\099", false),
+            ("98112", "\\98\\099", false),
+            // a trailing escape matches nothing
+            ("1", "9\\", false),
+        ];
+        for (value, mask, expected) in cases.iter() {
+            assert_eq!(matches_mask(value, mask), *expected, "{} ~ {}", value, mask);
+        }
+    }
+
+    #[test]
+    fn postal_codes() {
+        let cases = [
+            ("HU", "1051", true),
+            ("HU", "10511", false),
+            ("DE", "10115", true),
+            ("DE", "1011", false),
+            ("NL", "1012 AB", true),
+            ("NL", "1012AB", true),
+            ("NL", "1012 A1", false),
+            ("GB", "EC1A 1BB", true),
+            ("GB", "EC1A1BB", false),
+            ("CA", "K1A 0B1", true),
+            ("CA", "K1A0B1", true),
+            ("US", "10001", true),
+            ("US", "10001-1234", true),
+            ("US", "1000", false),
+            // literal prefixes are checked letter by letter
+            ("AD", "AD500", true),
+            ("AD", "XD500", false),
+            ("AD", "AA500", false),
+            ("AZ", "AZ 1000", true),
+            ("AZ", "BZ 1000", false),
+            ("AZ", "AA1000", false),
+            ("LV", "LV-1050", true),
+            ("LV", "LT-1050", false),
+            ("MC", "98000", true),
+            ("MC", "18000", false),
+            ("SM", "47890", true),
+            ("SM", "47990", false),
+            ("SI", "SI-1000", true),
+            ("SI", "SL-1000", false),
+        ];
+        for (code, zip, expected) in cases.iter() {
+            let cnt = Country::find(code).unwrap();
+            assert_eq!(cnt.valid_postal(zip), *expected, "{} {}", code, zip);
+        }
+    }
+
+    #[test]
+    fn vat_ids() {
+        let cases = [
+            ("HU", "HU12345678", true),
+            ("HU", "hu 1234 5678", true),
+            ("HU", "12345678", false),
+            ("HU", "DE12345678", false),
+            ("AT", "ATU12345678", true),
+            ("AT", "AT12345678", false),
+            ("AT", "ATX12345678", false),
+            ("FR", "FRAB123456789", true),
+            ("IE", "IE1234567T", true),
+            ("IE", "IE1234567TW", true),
+            ("NL", "NL123456789B01", true),
+            ("NL", "NL123456789X01", false),
+            // only EU member states have VAT ids that can be checked
+            ("US", "US123456789", false),
+            ("GB", "GB123456789", false),
+        ];
+        for (code, vat, expected) in cases.iter() {
+            let cnt = Country::find(code).unwrap();
+            assert_eq!(cnt.valid_vat(vat), *expected, "{} {}", code, vat);
+        }
+    }
+
+    #[test]
+    fn hints_leave_out_escapes() {
+        assert_eq!(
+            Country::find("AZ").unwrap().postal_hint(),
+            "AZ 9999, AZ9999"
+        );
+        assert_eq!(Country::find("MC").unwrap().postal_hint(), "98099");
+    }
+}
@@ -2,26 +2,183 @@ use actix_web::Error;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 
-use crate::db::AppState;
-use crate::modules::meta::default_meta;
+use chrono::{DateTime, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamptz};
+use futures::future::Future;
+
+use crate::db::{AppState, Conn};
+use crate::modules::access::allowed;
+use crate::modules::meta::{default_meta, Script};
+use crate::modules::user::impersonate::RequestImpersonation;
 use crate::render::Failure;
 use crate::utils::http_ok;
 
-fn index_render() -> Result<String, Failure> {
+/// Number of weeks shown on the completed todos chart, the current one included
+const WEEKS: i64 = 12;
+/// Number of contributors listed
+const TOP_CONTRIBUTORS: i64 = 5;
+
+/// The projects by status, the rules of the status are in the `project_statuses` view
+const STATUS_SQL: &str = r#"
+SELECT s.status, COUNT(*) AS count
+FROM projects p
+JOIN project_statuses s ON s.project_id = p.uuid
+WHERE p.team_id = $1 AND p.deleted_at IS NULL AND NOT p.is_template
+GROUP BY s.status
+ORDER BY s.status
+"#;
+
+const WEEKLY_SQL: &str = r#"
+SELECT to_char(w.week, 'IYYY-"W"IW') AS week, COUNT(td.id) AS count
+FROM generate_series(
+  date_trunc('week', now()) - make_interval(weeks => ($2 - 1)::int),
+  date_trunc('week', now()),
+  interval '1 week'
+) AS w(week)
+LEFT JOIN (
+  SELECT td.id, td.completed_at FROM todos td
+  JOIN projects p ON p.uuid = td.project_id
+  WHERE p.team_id = $1 AND NOT p.is_template AND td.completed AND td.deleted_at IS NULL
+) td ON date_trunc('week', td.completed_at) = w.week
+GROUP BY w.week
+ORDER BY w.week
+"#;
+
+const OVERDUE_SQL: &str = r#"
+SELECT p.projectid, p.title, p.end_date, s.total_todos - s.done_todos AS open_todos
+FROM projects p
+JOIN project_statuses s ON s.project_id = p.uuid
+WHERE p.team_id = $1 AND p.deleted_at IS NULL AND NOT p.is_template AND s.status = 'overdue'
+ORDER BY p.end_date
+"#;
+
+const CONTRIBUTORS_SQL: &str = r#"
+SELECT um.display, COUNT(*) AS count
+FROM todos td
+JOIN projects p ON p.uuid = td.project_id
+JOIN user_meta um ON um.user_id = td.completed_by
+WHERE p.team_id = $1 AND NOT p.is_template AND td.completed AND td.deleted_at IS NULL
+GROUP BY um.user_id, um.display
+ORDER BY count DESC, um.display
+LIMIT $2
+"#;
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct StatusCount {
+    #[sql_type = "Text"]
+    pub status: String,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct WeekCount {
+    #[sql_type = "Text"]
+    pub week: String,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct OverdueProject {
+    #[sql_type = "BigInt"]
+    pub projectid: i64,
+    #[sql_type = "Text"]
+    pub title: String,
+    #[sql_type = "Nullable<Timestamptz>"]
+    pub end_date: Option<DateTime<Utc>>,
+    #[sql_type = "BigInt"]
+    pub open_todos: i64,
+}
+
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct Contributor {
+    #[sql_type = "Text"]
+    pub display: String,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+/// Statistics of the selected team, served to `static/dashboard.js`
+#[derive(Serialize, Debug, Clone)]
+pub struct DashboardData {
+    pub statuses: Vec<StatusCount>,
+    pub weeks: Vec<WeekCount>,
+    pub overdue: Vec<OverdueProject>,
+    pub contributors: Vec<Contributor>,
+}
+impl DashboardData {
+    fn load(conn: &PgConnection, org_id: i64) -> QueryResult<Self> {
+        use diesel::sql_query;
+        Ok(DashboardData {
+            statuses: sql_query(STATUS_SQL).bind::<BigInt, _>(org_id).load(conn)?,
+            weeks: sql_query(WEEKLY_SQL)
+                .bind::<BigInt, _>(org_id)
+                .bind::<BigInt, _>(WEEKS)
+                .load(conn)?,
+            overdue: sql_query(OVERDUE_SQL)
+                .bind::<BigInt, _>(org_id)
+                .load(conn)?,
+            contributors: sql_query(CONTRIBUTORS_SQL)
+                .bind::<BigInt, _>(org_id)
+                .bind::<BigInt, _>(TOP_CONTRIBUTORS)
+                .load(conn)?,
+        })
+    }
+}
+
+fn index_render(impersonating: &Option<String>) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let links = crate::menu::default_menu();
     let mut list = ructe_block_res!(crate::templates::team::dashboard)?;
     list.push_str(r#"<script src="/static/dashboard.js" charset="utf-8"></script>"#);
-    let meta = default_meta("Dashboard");
+    let mut meta = default_meta("Dashboard");
+    meta.add_script(Script::with_external(
+        "https://cdnjs.cloudflare.com/ajax/libs/Chart.js/2.7.3/Chart.min.js",
+        "sha256-oSgtFCCmHWRPQ/JmR4OoZ3Xke1Pw4v50uh6pLcu+fIc=",
+    ));
     ructe_page_res!(
         crate::templates::navigation::frame,
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
 
-pub fn index(_req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
-    http_ok(index_render())
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    if crate::modules::team::current(req)?.is_none() {
+        return Ok(HttpResponse::Found()
+            .header("location", "/team/select")
+            .finish());
+    }
+    http_ok(index_render(&req.impersonating()))
+}
+
+/// The statistics of the selected team as JSON, aggregated on the read connection
+pub fn data(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    let org = match crate::modules::team::current(req)? {
+        Some(org_id) => match super::load(req, org_id) {
+            Ok(org) => org,
+            Err(_) => return Ok(HttpResponse::NotFound().finish()),
+        },
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    if !allowed(req, org.access_control_id).read {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let conn = match req.state().rdb.send(Conn {}).wait() {
+        Ok(Ok(conn)) => conn,
+        _ => return Ok(HttpResponse::ServiceUnavailable().finish()),
+    };
+    match DashboardData::load(&conn, org.id) {
+        Ok(stats) => Ok(HttpResponse::Ok().json(stats)),
+        Err(e) => {
+            error!("Dashboard statistics of team {} failed: {:?}", org.id, e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
 }
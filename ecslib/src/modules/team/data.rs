@@ -1,7 +1,18 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::modules::navigation::{EditableField, InputType, Link};
+use crate::modules::timezone::validate_name as validate_timezone;
 use crate::modules::user::User;
 use crate::schema::teams;
 
-#[derive(Insertable, AsChangeset, Queryable, Associations, Debug, Serialize, Deserialize, Clone)]
+use super::country::{Country, COUNTRIES};
+
+#[derive(
+    Insertable, AsChangeset, Queryable, Associations, Debug, Serialize, Deserialize, Clone,
+)]
 #[belongs_to(User)]
 #[table_name = "teams"]
 #[primary_key("id")]
@@ -16,16 +27,255 @@ pub struct Team {
     pub billing_city: String,
     pub billing_country: String,
     pub billing_zip: String,
+    pub billing_vat: Option<String>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub timezone: Option<String>,
 }
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl Team {
+    /// Archived teams are kept read only and are not offered for selection
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+}
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Validate)]
 pub struct TeamData {
     // pub access_control_id: ::uuid::Uuid,
     // pub user_id: i64,
+    #[validate(length(min = "1", max = "200", message = "A title is required"))]
     pub title: String,
     pub content: String,
+    #[validate(length(min = "1", max = "200", message = "A billing name is required"))]
     pub billing_name: String,
+    #[validate(length(min = "1", max = "200", message = "A billing address is required"))]
     pub billing_address: String,
+    #[validate(length(min = "1", max = "100", message = "A billing city is required"))]
     pub billing_city: String,
+    #[validate(custom = "validate_country")]
     pub billing_country: String,
+    #[validate(length(max = "20", message = "The postal code is too long"))]
     pub billing_zip: String,
+    pub billing_vat: Option<String>,
+    #[serde(default)]
+    #[validate(custom = "validate_timezone")]
+    pub timezone: String,
+}
+
+fn validate_country(code: &str) -> Result<(), ValidationError> {
+    match Country::find(code) {
+        Some(_) => Ok(()),
+        None => {
+            let mut err = ValidationError::new("billing_country");
+            err.message = Some("Select a country".into());
+            Err(err)
+        }
+    }
+}
+
+/// Rules depending on the country, every one broken is added to the errors of its field.
+/// A schema validation would stop at the first of them.
+fn validate_billing(data: &TeamData, errs: &mut ValidationErrors) {
+    let cnt = match Country::find(&data.billing_country) {
+        Some(cnt) => cnt,
+        None => return,
+    };
+    if !cnt.valid_postal(data.billing_zip.trim()) {
+        let mut err = ValidationError::new("billing_zip");
+        err.message = Some(
+            format!(
+                "Postal codes of {} look like {}",
+                cnt.name,
+                cnt.postal_hint()
+            )
+            .into(),
+        );
+        errs.add("billing_zip", err);
+    }
+    if let Some(vat) = data.vat() {
+        if !cnt.valid_vat(&vat) {
+            let mut err = ValidationError::new("billing_vat");
+            err.message = Some(
+                match cnt.vat_prefix {
+                    Some(pfx) => format!("Not a valid {} VAT id, it starts with {}", cnt.name, pfx),
+                    None => format!(
+                        "VAT ids can only be checked for EU countries, not {}",
+                        cnt.name
+                    ),
+                }
+                .into(),
+            );
+            errs.add("billing_vat", err);
+        }
+    }
+}
+
+impl TeamData {
+    /// The VAT id as stored: without spaces, upper case, `None` when left empty
+    pub fn vat(&self) -> Option<String> {
+        let vat: String = self
+            .billing_vat
+            .as_ref()?
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        if vat.is_empty() {
+            None
+        } else {
+            Some(vat.to_uppercase())
+        }
+    }
+
+    /// The IANA name of the timezone, `None` when left empty
+    pub fn timezone(&self) -> Option<String> {
+        let name = self.timezone.trim();
+        if name.is_empty() {
+            None
+        } else {
+            Some(name.to_owned())
+        }
+    }
+
+    /// Validation messages by field name, for rendering them next to the fields
+    pub fn errors(&self) -> HashMap<String, String> {
+        let mut errs = match self.validate() {
+            Ok(()) => ValidationErrors::new(),
+            Err(errs) => errs,
+        };
+        validate_billing(self, &mut errs);
+        error_map(&errs)
+    }
+
+    /// The fields of the `navigation::edit` form, filled from this data
+    pub fn fields(&self, errors: &HashMap<String, String>) -> Vec<EditableField> {
+        let mut countries: Vec<&Country> = COUNTRIES.iter().collect();
+        countries.sort_by_key(|cnt| cnt.name);
+        let country_links = countries
+            .into_iter()
+            .map(|cnt| Link::new(cnt.name, cnt.code))
+            .collect();
+        let field = |input_type: InputType, title: &str, name: &str, value: &str| EditableField {
+            input_type,
+            title: title.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            links: Vec::new(),
+            required: false,
+            error: errors.get(name).cloned(),
+        };
+        let mut country = field(
+            InputType::Select,
+            "Billing Country",
+            "billing_country",
+            &self.billing_country,
+        );
+        country.links = country_links;
+        country.required = true;
+        vec![
+            field(InputType::Input, "Title", "title", &self.title),
+            field(InputType::TextArea, "Content", "content", &self.content),
+            field(
+                InputType::Input,
+                "Billing Name",
+                "billing_name",
+                &self.billing_name,
+            ),
+            field(
+                InputType::Input,
+                "Billing Address",
+                "billing_address",
+                &self.billing_address,
+            ),
+            field(
+                InputType::Input,
+                "Billing City",
+                "billing_city",
+                &self.billing_city,
+            ),
+            country,
+            field(
+                InputType::Input,
+                "Billing Zip",
+                "billing_zip",
+                &self.billing_zip,
+            ),
+            field(
+                InputType::Input,
+                "Billing VAT id",
+                "billing_vat",
+                &self.billing_vat.clone().unwrap_or_default(),
+            ),
+            field(InputType::Input, "Timezone", "timezone", &self.timezone),
+        ]
+    }
+}
+
+impl<'a> From<&'a Team> for TeamData {
+    fn from(org: &'a Team) -> Self {
+        TeamData {
+            title: org.title.clone(),
+            content: org.content.clone(),
+            billing_name: org.billing_name.clone(),
+            billing_address: org.billing_address.clone(),
+            billing_city: org.billing_city.clone(),
+            billing_country: org.billing_country.clone(),
+            billing_zip: org.billing_zip.clone(),
+            billing_vat: org.billing_vat.clone(),
+            timezone: org.timezone.clone().unwrap_or_default(),
+        }
+    }
+}
+
+fn error_map(errs: &ValidationErrors) -> HashMap<String, String> {
+    let mut res = HashMap::new();
+    for (field, field_errs) in errs.field_errors() {
+        for err in field_errs.iter() {
+            let name = field.to_string();
+            let msg = match &err.message {
+                Some(msg) => msg.to_string(),
+                None => "Invalid value".to_string(),
+            };
+            res.entry(name).or_insert(msg);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TeamData;
+
+    fn billing(country: &str, zip: &str, vat: &str) -> TeamData {
+        TeamData {
+            title: "Team".to_string(),
+            billing_name: "Name".to_string(),
+            billing_address: "Street 1".to_string(),
+            billing_city: "City".to_string(),
+            billing_country: country.to_string(),
+            billing_zip: zip.to_string(),
+            billing_vat: Some(vat.to_string()),
+            ..TeamData::default()
+        }
+    }
+
+    #[test]
+    fn valid_billing_has_no_errors() {
+        assert!(billing("HU", "1051", "HU12345678").errors().is_empty());
+        assert!(billing("US", "10001", "").errors().is_empty());
+    }
+
+    #[test]
+    fn every_billing_error_is_reported() {
+        let errors = billing("HU", "10511", "HU123").errors();
+        assert!(errors.contains_key("billing_zip"));
+        assert!(errors.contains_key("billing_vat"));
+    }
+
+    #[test]
+    fn field_and_billing_errors_are_reported_together() {
+        let mut data = billing("DE", "1234", "DE123456789");
+        data.title = String::new();
+        let errors = data.errors();
+        assert!(errors.contains_key("title"));
+        assert!(errors.contains_key("billing_zip"));
+        assert!(!errors.contains_key("billing_vat"));
+    }
 }
@@ -0,0 +1,180 @@
+use actix_web::middleware::identity::RequestIdentity;
+use actix_web::middleware::session::RequestSession;
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use chrono::Utc;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::db::AppState;
+use crate::modules::meta::default_meta;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::render::Failure;
+use crate::schema::{
+    access_control, access_group_members, access_groups, access_rules, api_keys, projects,
+    team_groups, team_project_counters, teams, todos,
+};
+use crate::utils::{http_ok, write_conn};
+
+use super::data::Team;
+use super::members::owned_team;
+
+/// What `access_control.frozen` is set to for the entries of deleted teams,
+/// the entries themselves are kept for the audit trail
+const FROZEN_DELETED: &str = "deleted";
+
+fn index_render(
+    org: &Team,
+    error: &Option<String>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let cnt = ructe_block_res!(crate::templates::team::delete, org, error)?;
+    let meta = default_meta("Delete Team");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+/// Archiving and deleting is offered on the same page, both are up to the owner
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    match owned_team(req) {
+        Some(org) => http_ok(index_render(&org, &None, &req.impersonating())),
+        None => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
+
+/// Forgets the team selected in the session when it is the given one
+fn deselect(req: &HttpRequest<AppState>, org: &Team) {
+    if let Ok(Some(org_id)) = req.session().get::<i64>("org") {
+        if org_id == org.id {
+            req.session().remove("org");
+        }
+    }
+}
+
+fn set_archived(req: &HttpRequest<AppState>, archive: bool) -> HttpResponse {
+    let org = match owned_team(req) {
+        Some(org) => org,
+        None => return HttpResponse::Forbidden().finish(),
+    };
+    let archived = if archive { Some(Utc::now()) } else { None };
+    let conn = match write_conn(req) {
+        Ok(conn) => conn,
+        Err(e) => return HttpResponse::from_error(e),
+    };
+    let res = diesel::update(teams::table.filter(teams::id.eq(org.id)))
+        .set(teams::archived_at.eq(archived))
+        .execute(&conn);
+    debug!("{:?}", res);
+    if archive {
+        deselect(req, &org);
+    }
+    HttpResponse::Found()
+        .header("location", "/team/list")
+        .finish()
+}
+
+pub fn archive(req: &HttpRequest<AppState>) -> HttpResponse {
+    set_archived(req, true)
+}
+
+pub fn unarchive(req: &HttpRequest<AppState>) -> HttpResponse {
+    set_archived(req, false)
+}
+
+/// Removes the team with everything hanging on it: todos, projects with their counter, api keys,
+/// its role groups with their members and rules. Run it in a transaction.
+pub fn purge(conn: &PgConnection, org: &Team, actor: &str) -> QueryResult<()> {
+    use diesel::delete;
+
+    let team_projects = projects::table
+        .filter(projects::team_id.eq(org.id))
+        .select(projects::uuid);
+    delete(todos::table.filter(todos::project_id.eq_any(team_projects))).execute(conn)?;
+    delete(projects::table.filter(projects::team_id.eq(org.id))).execute(conn)?;
+    delete(team_project_counters::table.filter(team_project_counters::team_id.eq(org.id)))
+        .execute(conn)?;
+
+    let mut entries: Vec<i64> = delete(api_keys::table.filter(api_keys::team_id.eq(org.id)))
+        .returning(api_keys::access_control_id)
+        .get_results(conn)?;
+
+    let groups: Vec<i64> = delete(team_groups::table.filter(team_groups::team_id.eq(org.id)))
+        .returning(team_groups::access_group_id)
+        .get_results(conn)?;
+    entries.extend(
+        delete(
+            access_group_members::table
+                .filter(access_group_members::access_group_id.eq_any(groups.clone())),
+        )
+        .returning(access_group_members::access_control_id)
+        .get_results::<i64>(conn)?,
+    );
+    delete(
+        access_rules::table.filter(
+            access_rules::access_group_id
+                .eq_any(groups.clone())
+                .or(access_rules::access_control_id.eq(org.access_control_id)),
+        ),
+    )
+    .execute(conn)?;
+    entries.extend(
+        delete(access_groups::table.filter(access_groups::id.eq_any(groups)))
+            .returning(access_groups::access_control_id)
+            .get_results::<i64>(conn)?,
+    );
+
+    delete(teams::table.filter(teams::id.eq(org.id))).execute(conn)?;
+
+    entries.push(org.access_control_id);
+    diesel::update(access_control::table.filter(access_control::id.eq_any(entries)))
+        .set((
+            access_control::frozen.eq(FROZEN_DELETED),
+            access_control::last_update.eq(Utc::now()),
+            access_control::updated_by.eq(actor),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DeleteParams {
+    confirm: String,
+}
+/// Deletes the team for good, once its title has been typed in as confirmation
+pub fn delete(
+    (req, form): (HttpRequest<AppState>, Form<DeleteParams>),
+) -> Result<HttpResponse, Error> {
+    let org = match owned_team(&req) {
+        Some(org) => org,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    if form.confirm.trim() != org.title {
+        let error = Some("The title typed in does not match the title of the team".to_string());
+        return http_ok(index_render(&org, &error, &req.impersonating()));
+    }
+    let actor = req.identity().unwrap_or_default();
+    let conn = write_conn(&req)?;
+    let res = conn.transaction(|| purge(&conn, &org, &actor));
+    debug!("{:?}", res);
+    match res {
+        Ok(()) => {
+            info!("Team {} ({}) deleted by {}", org.id, org.title, actor);
+            deselect(&req, &org);
+            Ok(HttpResponse::Found()
+                .header("location", "/team/list")
+                .finish())
+        }
+        Err(e) => {
+            error!("Deleting team {} failed: {:?}", org.id, e);
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use futures::future::Future;
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
+
+use crate::db::{AppState, SQuery, WQuery};
+use crate::modules::access::access_control_entry;
+use crate::modules::meta::default_meta;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
+use crate::render::Failure;
+use crate::schema::api_keys::dsl::*;
+use crate::utils::{http_ok, write_conn};
+
+use super::data::Team;
+use super::role;
+
+/// Overlap of a rotated key with its replacement, unless asked otherwise
+const DEFAULT_OVERLAP_HOURS: i64 = 24 * 7;
+
+/// What an api key may be used for, every scope includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ApiScope {
+    Read,
+    Toggle,
+    Full,
+}
+impl ApiScope {
+    pub fn all() -> Vec<ApiScope> {
+        vec![ApiScope::Read, ApiScope::Toggle, ApiScope::Full]
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Toggle => "toggle",
+            ApiScope::Full => "full",
+        }
+    }
+    pub fn describe(self) -> &'static str {
+        match self {
+            ApiScope::Read => "Read only",
+            ApiScope::Toggle => "Read and toggle todos",
+            ApiScope::Full => "Full access",
+        }
+    }
+    pub fn parse(scp: &str) -> Option<ApiScope> {
+        ApiScope::all().into_iter().find(|s| s.as_str() == scp)
+    }
+    pub fn allows(self, required: ApiScope) -> bool {
+        self >= required
+    }
+}
+
+#[derive(Queryable, Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKey {
+    pub id: i64,
+    pub team_id: i64,
+    pub key_hash: String,
+    pub access_control_id: i64,
+    pub name: String,
+    pub prefix: String,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+impl ApiKey {
+    pub fn scope(&self) -> Option<ApiScope> {
+        ApiScope::parse(&self.scope)
+    }
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at.map_or(true, |exp| exp > Utc::now())
+    }
+    pub fn status(&self) -> &'static str {
+        if self.revoked_at.is_some() {
+            "revoked"
+        } else if self.is_active() {
+            "active"
+        } else {
+            "expired"
+        }
+    }
+    pub fn created(&self) -> String {
+        self.created_at.format("%Y-%m-%d %H:%M").to_string()
+    }
+    pub fn expires(&self) -> String {
+        display_time(self.expires_at, "never")
+    }
+    pub fn last_used(&self) -> String {
+        display_time(self.last_used_at, "not yet")
+    }
+}
+
+fn display_time(time: Option<DateTime<Utc>>, none: &str) -> String {
+    match time {
+        Some(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+        None => none.to_owned(),
+    }
+}
+
+/// A freshly generated key, `raw` is never stored and only shown once
+pub struct NewKey {
+    pub raw: String,
+    pub prefix: String,
+    pub hash: String,
+}
+impl NewKey {
+    fn generate() -> Self {
+        let prefix = format!("ecs_{:08x}", rand::random::<u32>());
+        let raw = format!("{}_{:032x}", prefix, rand::random::<u128>());
+        let hash = hash_key(&raw);
+        NewKey { raw, prefix, hash }
+    }
+}
+
+/// Keys are long and random, a plain digest is enough to keep them out of the database.
+/// Nothing of it is logged.
+fn hash_key(raw: &str) -> String {
+    format!("{:x}", Sha256::digest(raw.as_bytes()))
+}
+
+/// Looks up an active key with at least the `required` scope, and records its use
+pub fn verify(req: &HttpRequest<AppState>, raw: &str, required: ApiScope) -> Option<ApiKey> {
+    let hash = hash_key(raw.trim());
+    let query = api_keys
+        .filter(key_hash.eq(hash))
+        .filter(revoked_at.is_null())
+        .filter(expires_at.is_null().or(expires_at.gt(Utc::now())));
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<ApiKey>,
+    };
+    let found = req.state().rdb.send(sel).wait().ok()?.ok()?;
+    let ak = found.into_iter().next()?;
+    if !ak.scope()?.allows(required) {
+        return None;
+    }
+    // archived teams are read only, whatever the scope of their keys
+    if required > ApiScope::Read && super::load(req, ak.team_id).ok()?.is_archived() {
+        return None;
+    }
+    let query =
+        diesel::update(api_keys.filter(id.eq(ak.id))).set(last_used_at.eq(Some(Utc::now())));
+    let upd = WQuery {
+        query,
+        phantom: PhantomData::<ApiKey>,
+    };
+    let res = req.state().wdb.send(upd).wait();
+    debug!("{:?}", res);
+    Some(ak)
+}
+
+/// The team from the path, as long as the user is its owner or one of its admins
+fn managed_team(req: &HttpRequest<AppState>) -> Option<Team> {
+    let org_id = req.match_info().get("id")?.parse::<i64>().ok()?;
+    let usr_meta = UserMeta::current(req).ok()?;
+    let org = super::load(req, org_id).ok()?;
+    match role::role_of(req, org.id, usr_meta.user_id) {
+        Some(role) if role.manages_keys() => Some(org),
+        _ => None,
+    }
+}
+
+fn keys_url(org: &Team) -> String {
+    format!("/team/{}/keys", org.id)
+}
+
+fn load_keys(req: &HttpRequest<AppState>, org: &Team) -> Vec<ApiKey> {
+    let query = api_keys.filter(team_id.eq(org.id)).order(created_at.desc());
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<ApiKey>,
+    };
+    match req.state().rdb.send(sel).wait() {
+        Ok(Ok(keys)) => keys,
+        _ => Vec::new(),
+    }
+}
+
+fn load_key(req: &HttpRequest<AppState>, org: &Team) -> Option<ApiKey> {
+    let kid = req.match_info().get("kid")?.parse::<i64>().ok()?;
+    load_keys(req, org).into_iter().find(|ak| ak.id == kid)
+}
+
+fn index_render(
+    org: &Team,
+    keys: &[ApiKey],
+    created: &Option<String>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let scopes = ApiScope::all();
+    let cnt = ructe_block_res!(
+        crate::templates::team::keys,
+        org,
+        keys,
+        &scopes,
+        created,
+        DEFAULT_OVERLAP_HOURS
+    )?;
+    let meta = default_meta("Team API Keys");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    match managed_team(req) {
+        Some(org) => {
+            let keys = load_keys(req, &org);
+            http_ok(index_render(&org, &keys, &None, &req.impersonating()))
+        }
+        None => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
+
+/// Stores a new key of the team, returns the key to show to the user
+fn insert_key(
+    req: &HttpRequest<AppState>,
+    org: &Team,
+    key_name: &str,
+    key_scope: ApiScope,
+    expiry: Option<DateTime<Utc>>,
+) -> Option<String> {
+    let new_key = NewKey::generate();
+    let access = access_control_entry(req).ok()?;
+    let query = diesel::insert_into(api_keys).values((
+        team_id.eq(org.id),
+        key_hash.eq(new_key.hash),
+        crate::schema::api_keys::access_control_id.eq(access.id),
+        name.eq(key_name),
+        prefix.eq(new_key.prefix),
+        scope.eq(key_scope.as_str()),
+        expires_at.eq(expiry),
+    ));
+    let ins = WQuery {
+        query,
+        phantom: PhantomData::<ApiKey>,
+    };
+    match req.state().wdb.send(ins).wait() {
+        Ok(Ok(_)) => Some(new_key.raw),
+        res => {
+            error!("Creating api key failed: {:?}", res);
+            None
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct KeyParams {
+    name: String,
+    scope: String,
+    expires_in_days: Option<String>,
+}
+pub fn create(
+    (req, form): (HttpRequest<AppState>, Form<KeyParams>),
+) -> Result<HttpResponse, Error> {
+    let org = match managed_team(&req) {
+        Some(ref org) if org.is_archived() => return Ok(HttpResponse::Forbidden().finish()),
+        Some(org) => org,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let key_scope = match ApiScope::parse(&form.scope) {
+        Some(key_scope) => key_scope,
+        None => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    let key_name = form.name.trim();
+    if key_name.is_empty() {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+    let expiry = match form.expires_in_days.as_ref().map(|d| d.trim()) {
+        None | Some("") => None,
+        Some(days) => match days.parse::<i64>() {
+            Ok(days) if days > 0 => Some(Utc::now() + Duration::days(days)),
+            _ => return Ok(HttpResponse::BadRequest().finish()),
+        },
+    };
+    let created = insert_key(&req, &org, key_name, key_scope, expiry);
+    let keys = load_keys(&req, &org);
+    http_ok(index_render(&org, &keys, &created, &req.impersonating()))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RotateParams {
+    overlap_hours: Option<String>,
+}
+/// Replaces a key with a new one of the same name and scope, the old one keeps working
+/// for the overlap period so clients can be switched over
+pub fn rotate(
+    (req, form): (HttpRequest<AppState>, Form<RotateParams>),
+) -> Result<HttpResponse, Error> {
+    let org = match managed_team(&req) {
+        Some(ref org) if org.is_archived() => return Ok(HttpResponse::Forbidden().finish()),
+        Some(org) => org,
+        None => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    let old = match load_key(&req, &org) {
+        Some(old) if old.is_active() => old,
+        _ => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    let overlap = match form.overlap_hours.as_ref().map(|h| h.trim()) {
+        None | Some("") => DEFAULT_OVERLAP_HOURS,
+        Some(hours) => match hours.parse::<i64>() {
+            Ok(hours) if hours >= 0 => hours,
+            _ => return Ok(HttpResponse::BadRequest().finish()),
+        },
+    };
+    let new_key = NewKey::generate();
+    let access = match access_control_entry(&req) {
+        Ok(access) => access,
+        Err(_) => return Ok(HttpResponse::InternalServerError().finish()),
+    };
+    let cutoff = Utc::now() + Duration::hours(overlap);
+    let old_expiry = match old.expires_at {
+        Some(exp) if exp < cutoff => exp,
+        _ => cutoff,
+    };
+    let conn = write_conn(&req)?;
+    let res = conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::insert_into(api_keys)
+            .values((
+                team_id.eq(org.id),
+                key_hash.eq(&new_key.hash),
+                crate::schema::api_keys::access_control_id.eq(access.id),
+                name.eq(&old.name),
+                prefix.eq(&new_key.prefix),
+                scope.eq(&old.scope),
+                expires_at.eq(old.expires_at),
+            ))
+            .execute(&conn)?;
+        diesel::update(api_keys.filter(id.eq(old.id)))
+            .set(expires_at.eq(old_expiry))
+            .execute(&conn)?;
+        Ok(())
+    });
+    debug!("{:?}", res);
+    let created = match res {
+        Ok(()) => Some(new_key.raw),
+        Err(_) => None,
+    };
+    let keys = load_keys(&req, &org);
+    http_ok(index_render(&org, &keys, &created, &req.impersonating()))
+}
+
+pub fn revoke(req: &HttpRequest<AppState>) -> HttpResponse {
+    let org = match managed_team(req) {
+        Some(org) => org,
+        None => return HttpResponse::Forbidden().finish(),
+    };
+    if let Some(ak) = load_key(req, &org) {
+        let query =
+            diesel::update(api_keys.filter(id.eq(ak.id))).set(revoked_at.eq(Some(Utc::now())));
+        let upd = WQuery {
+            query,
+            phantom: PhantomData::<ApiKey>,
+        };
+        let res = req.state().wdb.send(upd).wait();
+        debug!("{:?}", res);
+    }
+    HttpResponse::Found()
+        .header("location", keys_url(&org))
+        .finish()
+}
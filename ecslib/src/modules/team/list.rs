@@ -1,24 +1,108 @@
-use actix_web::{Error, HttpRequest, HttpResponse};
+use actix_web::{Error, HttpRequest, HttpResponse, Query};
 
 use diesel::prelude::*;
 use futures::future::Future;
+use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
 
-use crate::db::{AppState, SQuery};
-use crate::modules::navigation::{Cell, CellContent, Link, ListContext, Permission, Row};
+use crate::db::{AppState, DbExecutorError, SQuery};
+use crate::modules::access::{allowed, RequestPermission};
+use crate::modules::navigation::{
+    Cell, CellContent, Link, ListContext, Pagination, Permission, PermissionSet, Row,
+};
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
 use crate::render::Failure;
 use crate::utils::http_ok;
 
 use super::data::Team;
+use super::role::{self, Role};
 use crate::modules::meta::default_meta;
 use crate::schema::teams::dsl::*;
+use crate::schema::{access_group_members, access_groups, projects, team_groups, user_meta};
 
-fn create_list(data: &[Team]) -> Vec<Row> {
+const PER_PAGE: i64 = 20;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ListParams {
+    pub q: Option<String>,
+    pub page: Option<i64>,
+}
+
+/// What is shown of a team beside its own columns
+#[derive(Debug, Clone)]
+struct TeamStats {
+    owners: HashMap<i64, String>,
+    members: HashMap<i64, usize>,
+    projects: HashMap<i64, usize>,
+    /// The role of the user in the teams, members and keys are managed by some roles only
+    roles: HashMap<i64, Role>,
+}
+impl TeamStats {
+    fn load(req: &HttpRequest<AppState>, data: &[Team]) -> Result<Self, DbExecutorError> {
+        let ids: Vec<i64> = data.iter().map(|org| org.id).collect();
+        let owner_ids: Vec<i64> = data.iter().map(|org| org.user_id).collect();
+
+        let query = user_meta::table
+            .filter(user_meta::user_id.eq_any(owner_ids))
+            .select((user_meta::user_id, user_meta::display));
+        let sel = SQuery {
+            select: query,
+            phantom: PhantomData::<(i64, String)>,
+        };
+        let owners = req.state().rdb.send(sel).wait()??.into_iter().collect();
+
+        let query = team_groups::table
+            .inner_join(access_groups::table.inner_join(access_group_members::table))
+            .filter(team_groups::team_id.eq_any(ids.clone()))
+            .select((team_groups::team_id, access_group_members::user_id));
+        let sel = SQuery {
+            select: query,
+            phantom: PhantomData::<(i64, i64)>,
+        };
+        let memberships: HashSet<(i64, i64)> =
+            req.state().rdb.send(sel).wait()??.into_iter().collect();
+        let mut members = HashMap::new();
+        for (org_id, _) in memberships {
+            *members.entry(org_id).or_insert(0) += 1;
+        }
+
+        let query = projects::table
+            .filter(projects::team_id.eq_any(ids))
+            .filter(projects::deleted_at.is_null())
+            .filter(projects::is_template.eq(false))
+            .select(projects::team_id);
+        let sel = SQuery {
+            select: query,
+            phantom: PhantomData::<i64>,
+        };
+        let mut project_counts = HashMap::new();
+        for org_id in req.state().rdb.send(sel).wait()?? {
+            *project_counts.entry(org_id).or_insert(0) += 1;
+        }
+        let roles = match UserMeta::current(req) {
+            Ok(usr_meta) => role::roles(req, usr_meta.user_id)?,
+            Err(_) => HashMap::new(),
+        };
+        Ok(TeamStats {
+            owners,
+            members,
+            projects: project_counts,
+            roles,
+        })
+    }
+}
+
+fn create_list(req: &HttpRequest<AppState>, data: &[Team], stats: &TeamStats) -> Vec<Row> {
     let mut res = Vec::new();
     for ent in data {
         let mut cells = Vec::new();
 
-        let title_cont = CellContent::new(ent.title.to_string());
+        let title_cont = if ent.is_archived() {
+            CellContent::new(format!("{} (archived)", ent.title))
+        } else {
+            CellContent::new(ent.title.to_string())
+        };
         let title_cell = Cell {
             title: "Title".to_string(),
             content: title_cont,
@@ -26,17 +110,33 @@ fn create_list(data: &[Team]) -> Vec<Row> {
         };
         cells.push(title_cell);
 
-        let billing_name_cont = CellContent::new(ent.billing_name.to_string());
-        let billing_name_cell = Cell {
-            title: "BillingName".to_string(),
-            content: billing_name_cont,
+        let owner = stats.owners.get(&ent.user_id).cloned().unwrap_or_default();
+        let owner_cell = Cell {
+            title: "Owner".to_string(),
+            content: CellContent::new(owner),
+            is_nullable: false,
+        };
+        cells.push(owner_cell);
+
+        let members = stats.members.get(&ent.id).cloned().unwrap_or(0);
+        let members_cell = Cell {
+            title: "Members".to_string(),
+            content: CellContent::new(members.to_string()),
             is_nullable: false,
         };
-        cells.push(billing_name_cell);
+        cells.push(members_cell);
+
+        let project_count = stats.projects.get(&ent.id).cloned().unwrap_or(0);
+        let projects_cell = Cell {
+            title: "Projects".to_string(),
+            content: CellContent::new(project_count.to_string()),
+            is_nullable: false,
+        };
+        cells.push(projects_cell);
 
         let ed = Link {
             visual: "Edit".to_string(),
-            url: format!("/team/{}", ent.id),
+            url: format!("/team/{}/edit", ent.id),
             active: false,
             icon: "fa-edit".to_string(),
             clearance: Permission::Edit,
@@ -44,60 +144,154 @@ fn create_list(data: &[Team]) -> Vec<Row> {
         };
         let del = Link {
             visual: "Delete".to_string(),
-            url: format!("/teams/{}", ent.id),
+            url: format!("/team/{}/delete", ent.id),
             active: false,
             icon: "fa-trash".to_string(),
             clearance: Permission::Delete,
             children: None,
         };
-        let links = vec![ed, del];
-        let row = Row { cells, links };
+        let mut links = vec![ed];
+        // the pages check the role rather than the permissions, so do the links
+        let team_role = stats.roles.get(&ent.id).cloned();
+        if team_role.map_or(false, Role::manages_members) {
+            links.push(Link {
+                visual: "Members".to_string(),
+                url: format!("/team/{}/members", ent.id),
+                active: false,
+                icon: "fa-users".to_string(),
+                clearance: Permission::Browse,
+                children: None,
+            });
+        }
+        if team_role.map_or(false, Role::manages_keys) {
+            links.push(Link {
+                visual: "API keys".to_string(),
+                url: format!("/team/{}/keys", ent.id),
+                active: false,
+                icon: "fa-key".to_string(),
+                clearance: Permission::Browse,
+                children: None,
+            });
+        }
+        links.push(del);
+        let row = Row {
+            cells,
+            links,
+            permission: Some(allowed(req, ent.access_control_id)),
+            confirm: None,
+        };
         res.push(row);
     }
     res
 }
-pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
-    let query = teams.filter(id.is_not_null());
+
+/// Access control entries the user may browse, empty without `PermissionCheck`
+fn browsable(req: &HttpRequest<AppState>) -> Vec<i64> {
+    match req.permission() {
+        Some(pmap) => pmap
+            .map()
+            .iter()
+            .filter(|(_, pset)| pset.browse)
+            .map(|(acid, _)| *acid)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Turns a search term into an `ILIKE` pattern, matching it literally anywhere in the title
+fn like_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// One page of the teams the user may browse, filtered by `search`
+fn load_page(
+    req: &HttpRequest<AppState>,
+    search: &str,
+    page: Option<i64>,
+) -> Result<(Vec<Team>, Pagination), DbExecutorError> {
+    let acids = browsable(req);
+    let pattern = like_pattern(search);
+
+    let query = teams
+        .filter(access_control_id.eq_any(acids.clone()))
+        .filter(title.ilike(pattern.clone()))
+        .count();
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<i64>,
+    };
+    let total = req.state().rdb.send(sel).wait()??;
+    let pagination = Pagination::new(page, PER_PAGE, total.first().cloned().unwrap_or(0), search);
+
+    let query = teams
+        .filter(access_control_id.eq_any(acids))
+        .filter(title.ilike(pattern))
+        .order((title.asc(), id.asc()))
+        .limit(pagination.per_page)
+        .offset(pagination.offset());
     let select = SQuery {
         select: query,
         phantom: PhantomData::<Team>,
     };
-    if let Ok(thing) = req
-        .state()
-        .rdb
-        .send(select)
-        .map_err(actix_web::Error::from)
-        .wait()
-    {
-        if let Ok(data) = thing {
-            let list = create_list(&data);
-            return http_ok(index_render(list));
+    let data = req.state().rdb.send(select).wait()??;
+    Ok((data, pagination))
+}
+
+pub fn index(
+    (req, params): (HttpRequest<AppState>, Query<ListParams>),
+) -> Result<HttpResponse, Error> {
+    let search = params.q.clone().unwrap_or_default().trim().to_owned();
+    let page = load_page(&req, &search, params.page).and_then(|(data, pagination)| {
+        let stats = TeamStats::load(&req, &data)?;
+        Ok((create_list(&req, &data, &stats), pagination))
+    });
+    match page {
+        Ok((list, pagination)) => http_ok(index_render(list, pagination, &req.impersonating())),
+        Err(e) => {
+            error!("Listing teams failed: {:?}", e);
+            Ok(HttpResponse::InternalServerError().finish())
         }
     }
-    Ok(HttpResponse::Ok().finish())
 }
-fn index_render(list: Vec<Row>) -> Result<String, Failure> {
+
+fn index_render(
+    list: Vec<Row>,
+    pagination: Pagination,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let links = crate::menu::default_menu();
     let ctx = ListContext {
         title: "Team".to_string(),
         head: "List of teams".to_string(),
-        search: false,
+        search: true,
+        sort: None,
     };
-    let perm = crate::modules::navigation::PermissionSet {
+    let perm = PermissionSet {
         browse: true,
         read: true,
-        edit: true,
+        edit: false,
         add: true,
-        delete: true,
+        delete: false,
     };
-    let list = ructe_block_res!(crate::templates::navigation::table, &list, &ctx, &perm)?;
+    let list = ructe_block_res!(
+        crate::templates::navigation::table,
+        &list,
+        &ctx,
+        &perm,
+        &Some(pagination)
+    )?;
     let meta = default_meta("List of Teams");
     ructe_page_res!(
         crate::templates::navigation::frame,
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
@@ -0,0 +1,183 @@
+use actix_web::middleware::identity::RequestIdentity;
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use crate::db::AppState;
+use crate::modules::meta::default_meta;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
+use crate::render::Failure;
+use crate::utils::{http_ok, write_conn};
+
+use super::data::Team;
+use super::role::{self, Member, Role};
+
+/// The team from the path, as long as the user acts as its owner
+pub(super) fn owned_team(req: &HttpRequest<AppState>) -> Option<Team> {
+    let org_id = req.match_info().get("id")?.parse::<i64>().ok()?;
+    let usr_meta = UserMeta::current(req).ok()?;
+    let org = super::load(req, org_id).ok()?;
+    match role::role_of(req, org.id, usr_meta.user_id) {
+        Some(role) if role.manages_members() => Some(org),
+        _ => None,
+    }
+}
+
+fn members_url(org: &Team) -> String {
+    format!("/team/{}/members", org.id)
+}
+
+fn index_render(
+    org: &Team,
+    members: &[Member],
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let roles = Role::assignable();
+    let cnt = ructe_block_res!(crate::templates::team::members, org, members, &roles)?;
+    let meta = default_meta("Team Members");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    match owned_team(req) {
+        Some(org) => {
+            let members = role::members(req, org.id).unwrap_or_default();
+            http_ok(index_render(&org, &members, &req.impersonating()))
+        }
+        None => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct InviteParams {
+    email: String,
+    role: String,
+}
+pub fn invite((req, form): (HttpRequest<AppState>, Form<InviteParams>)) -> HttpResponse {
+    let org = match owned_team(&req) {
+        Some(org) => org,
+        None => return HttpResponse::Forbidden().finish(),
+    };
+    let role = match Role::parse(&form.role) {
+        Some(Role::Owner) | None => return HttpResponse::BadRequest().finish(),
+        Some(role) => role,
+    };
+    if let Ok(usr_meta) = UserMeta::load(&req, form.email.trim().to_owned()) {
+        if role::role_of(&req, org.id, usr_meta.user_id).is_none() {
+            let actor = req.identity().unwrap_or_default();
+            let conn = match write_conn(&req) {
+                Ok(conn) => conn,
+                Err(e) => return HttpResponse::from_error(e),
+            };
+            let res =
+                conn.transaction(|| role::set_role(&conn, org.id, usr_meta.user_id, role, &actor));
+            debug!("{:?}", res);
+        }
+    }
+    HttpResponse::Found()
+        .header("location", members_url(&org))
+        .finish()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RoleParams {
+    role: String,
+}
+pub fn change_role((req, form): (HttpRequest<AppState>, Form<RoleParams>)) -> HttpResponse {
+    let org = match owned_team(&req) {
+        Some(org) => org,
+        None => return HttpResponse::Forbidden().finish(),
+    };
+    let usr_id = match req.match_info().get("uid").map(str::parse::<i64>) {
+        Some(Ok(usr_id)) => usr_id,
+        _ => return HttpResponse::BadRequest().finish(),
+    };
+    let role = match Role::parse(&form.role) {
+        Some(Role::Owner) | None => return HttpResponse::BadRequest().finish(),
+        Some(role) => role,
+    };
+    // the owner is only ever changed through a transfer
+    match role::role_of(&req, org.id, usr_id) {
+        Some(Role::Owner) | None => {}
+        Some(_) => {
+            let actor = req.identity().unwrap_or_default();
+            let conn = match write_conn(&req) {
+                Ok(conn) => conn,
+                Err(e) => return HttpResponse::from_error(e),
+            };
+            let res = conn.transaction(|| role::set_role(&conn, org.id, usr_id, role, &actor));
+            debug!("{:?}", res);
+        }
+    }
+    HttpResponse::Found()
+        .header("location", members_url(&org))
+        .finish()
+}
+
+pub fn remove(req: &HttpRequest<AppState>) -> HttpResponse {
+    let org = match owned_team(req) {
+        Some(org) => org,
+        None => return HttpResponse::Forbidden().finish(),
+    };
+    let usr_id = match req.match_info().get("uid").map(str::parse::<i64>) {
+        Some(Ok(usr_id)) => usr_id,
+        _ => return HttpResponse::BadRequest().finish(),
+    };
+    match role::role_of(req, org.id, usr_id) {
+        Some(Role::Owner) | None => {}
+        Some(_) => {
+            let conn = match write_conn(&req) {
+                Ok(conn) => conn,
+                Err(e) => return HttpResponse::from_error(e),
+            };
+            let res = role::remove(&conn, org.id, usr_id);
+            debug!("{:?}", res);
+        }
+    }
+    HttpResponse::Found()
+        .header("location", members_url(&org))
+        .finish()
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TransferParams {
+    user_id: i64,
+}
+pub fn transfer((req, form): (HttpRequest<AppState>, Form<TransferParams>)) -> HttpResponse {
+    let org = match owned_team(&req) {
+        Some(org) => org,
+        None => return HttpResponse::Forbidden().finish(),
+    };
+    // only existing members can become the owner
+    match role::role_of(&req, org.id, form.user_id) {
+        Some(Role::Owner) | None => HttpResponse::Found()
+            .header("location", members_url(&org))
+            .finish(),
+        Some(_) => {
+            let actor = req.identity().unwrap_or_default();
+            let conn = match write_conn(&req) {
+                Ok(conn) => conn,
+                Err(e) => return HttpResponse::from_error(e),
+            };
+            let res = role::transfer_owner(&conn, &org, form.user_id, &actor);
+            debug!("{:?}", res);
+            if res.is_ok() {
+                info!(
+                    "Team {} transferred from user {} to user {} by {}",
+                    org.id, org.user_id, form.user_id, actor
+                );
+            }
+            HttpResponse::Found()
+                .header("location", "/team/list")
+                .finish()
+        }
+    }
+}
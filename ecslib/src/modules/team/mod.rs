@@ -1,40 +1,57 @@
 pub mod add;
+pub mod country;
 pub mod dashboard;
 pub mod data;
+pub mod delete;
 pub mod edit;
+pub mod keys;
 pub mod list;
+pub mod members;
+pub mod role;
 pub mod select;
 
-use actix_web::{Form, HttpRequest, HttpResponse};
-// use actix_web::middleware::identity::RequestIdentity;
 use actix_web::middleware::session::RequestSession;
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
 
 use diesel::prelude::*;
 use futures::future::Future;
 use std::marker::PhantomData;
 
-// use crate::modules::user::UserMeta;
 use crate::db::{AppState, DbExecutorError, SQuery};
-
 use crate::modules::access::allowed;
+use crate::modules::navigation::PermissionSet;
+
 use crate::modules::team::data::Team;
+use crate::modules::user::UserMeta;
 use crate::schema::teams::dsl::*;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OrgId {
     org: i64,
+    /// Where to go once selected, only pages of the projects are accepted
+    #[serde(default)]
+    next: Option<String>,
 }
+/// Stores the selected team in the session, all project queries are scoped by it
 pub fn set((req, form): (HttpRequest<AppState>, Form<OrgId>)) -> HttpResponse {
-    if let Ok(org) = load(&req, form.org) {
-        let perm = allowed(&req, org.access_control_id);
-        if perm.read {
-            let res = req.session().set("org", org.id);
-            if res.is_err() {
-                return HttpResponse::ExpectationFailed().finish();
+    if let Ok(usr_meta) = UserMeta::current(&req) {
+        if let Ok(org) = load(&req, form.org) {
+            if !org.is_archived() && is_member(&req, &org, usr_meta.user_id) {
+                let res = req.session().set("org", org.id);
+                if res.is_err() {
+                    return HttpResponse::ExpectationFailed().finish();
+                }
+                let next = match &form.next {
+                    Some(next) if next.starts_with("/project/") => next.as_str(),
+                    _ => "/project/list",
+                };
+                return HttpResponse::Found().header("location", next).finish();
             }
         }
     }
-    HttpResponse::Ok().finish()
+    HttpResponse::Found()
+        .header("location", "/team/select")
+        .finish()
 }
 
 pub fn load(req: &HttpRequest<AppState>, org_id: i64) -> Result<Team, DbExecutorError> {
@@ -49,3 +66,53 @@ pub fn load(req: &HttpRequest<AppState>, org_id: i64) -> Result<Team, DbExecutor
     }
     Err(DbExecutorError::Unknown)
 }
+
+/// Teams the user is a member of, in any role, archived ones left out
+pub fn for_user(req: &HttpRequest<AppState>, usr_id: i64) -> Result<Vec<Team>, DbExecutorError> {
+    let query = teams
+        .filter(id.eq_any(role::team_ids(req, usr_id)?))
+        .filter(archived_at.is_null())
+        .order(title.asc());
+    let select = SQuery {
+        select: query,
+        phantom: PhantomData::<Team>,
+    };
+    Ok(req.state().rdb.send(select).wait()??)
+}
+
+pub fn is_member(req: &HttpRequest<AppState>, org: &Team, usr_id: i64) -> bool {
+    role::role_of(req, org.id, usr_id).is_some()
+}
+
+/// The team selected in the session, as long as the user is still a member of it
+pub fn current(req: &HttpRequest<AppState>) -> Result<Option<i64>, Error> {
+    if let Some(org_id) = req.session().get::<i64>("org")? {
+        if let Ok(usr_meta) = UserMeta::current(req) {
+            if role::role_of(req, org_id, usr_meta.user_id).is_some() {
+                return Ok(Some(org_id));
+            }
+        }
+        req.session().remove("org");
+    }
+    Ok(None)
+}
+
+/// The selected team as long as it can be changed, `None` once it has been archived
+pub fn writable(req: &HttpRequest<AppState>) -> Result<Option<i64>, Error> {
+    match current(req)? {
+        Some(org_id) => match load(req, org_id) {
+            Ok(ref org) if !org.is_archived() => Ok(Some(org_id)),
+            _ => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+/// What the user may do on the team and its projects, they are governed by its access control
+/// entry. Membership alone, as `writable` checks, says nothing about the role.
+pub fn permission(req: &HttpRequest<AppState>, org_id: i64) -> PermissionSet {
+    match load(req, org_id) {
+        Ok(org) => allowed(req, org.access_control_id),
+        Err(_) => PermissionSet::deny(),
+    }
+}
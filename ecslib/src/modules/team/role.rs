@@ -0,0 +1,284 @@
+use actix_web::HttpRequest;
+
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use futures::future::Future;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::db::{AppState, DbExecutorError, SQuery};
+use crate::modules::user::UserMeta;
+use crate::schema::{
+    access_control, access_group_members, access_groups, access_rules, team_groups, teams,
+    user_meta,
+};
+
+use super::data::Team;
+
+/// The role of a member in a team, every role is backed by an access group of the team
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Role {
+    Owner,
+    Admin,
+    Member,
+    Viewer,
+}
+
+impl Role {
+    pub fn all() -> Vec<Role> {
+        vec![Role::Owner, Role::Admin, Role::Member, Role::Viewer]
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Admin => "admin",
+            Role::Member => "member",
+            Role::Viewer => "viewer",
+        }
+    }
+    pub fn parse(role: &str) -> Option<Role> {
+        Role::all().into_iter().find(|r| r.as_str() == role)
+    }
+    /// The `access_rules` a role expands into on the access control entry of the team
+    pub fn access_types(self) -> &'static [&'static str] {
+        match self {
+            Role::Owner | Role::Admin => &["browse", "read", "edit", "add", "delete"],
+            Role::Member => &["browse", "read", "edit", "add"],
+            Role::Viewer => &["browse", "read"],
+        }
+    }
+    /// Owners manage the members and the roles of the team
+    pub fn manages_members(self) -> bool {
+        self == Role::Owner
+    }
+    /// Owners and admins manage the api keys of the team
+    pub fn manages_keys(self) -> bool {
+        self == Role::Owner || self == Role::Admin
+    }
+    /// Roles which can be given through invitation or role change, ownership is transferred
+    pub fn assignable() -> Vec<Role> {
+        vec![Role::Admin, Role::Member, Role::Viewer]
+    }
+}
+
+#[derive(Queryable, Debug, Serialize, Deserialize, Clone)]
+pub struct TeamGroup {
+    pub id: i64,
+    pub team_id: i64,
+    pub access_group_id: i64,
+    pub role: String,
+}
+
+/// A user of a team together with their role
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Member {
+    pub user: UserMeta,
+    pub role: Role,
+}
+
+/// Creates the role groups of a new team and makes `owner_id` its owner
+pub fn create_groups(
+    conn: &PgConnection,
+    org: &Team,
+    owner_id: i64,
+    actor: &str,
+) -> QueryResult<()> {
+    use diesel::insert_into;
+    for role in Role::all() {
+        let acc: i64 = insert_into(access_control::table)
+            .values((
+                access_control::created_by.eq(actor),
+                access_control::updated_by.eq(actor),
+            ))
+            .returning(access_control::id)
+            .get_result(conn)?;
+        let grp: i64 = insert_into(access_groups::table)
+            .values((
+                access_groups::name.eq(format!("team:{}:{}", org.id, role.as_str())),
+                access_groups::access_control_id.eq(acc),
+            ))
+            .returning(access_groups::id)
+            .get_result(conn)?;
+        insert_into(team_groups::table)
+            .values((
+                team_groups::team_id.eq(org.id),
+                team_groups::access_group_id.eq(grp),
+                team_groups::role.eq(role.as_str()),
+            ))
+            .execute(conn)?;
+        let rules: Vec<_> = role
+            .access_types()
+            .iter()
+            .map(|tp| {
+                (
+                    access_rules::access_group_id.eq(grp),
+                    access_rules::access_control_id.eq(org.access_control_id),
+                    access_rules::access_type.eq(*tp),
+                )
+            })
+            .collect();
+        insert_into(access_rules::table)
+            .values(&rules)
+            .execute(conn)?;
+        if role == Role::Owner {
+            insert_into(access_group_members::table)
+                .values((
+                    access_group_members::access_group_id.eq(grp),
+                    access_group_members::user_id.eq(owner_id),
+                    access_group_members::access_control_id.eq(acc),
+                ))
+                .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Memberships of a user, as pairs of team id and role
+fn memberships(
+    req: &HttpRequest<AppState>,
+    usr_id: i64,
+) -> Result<Vec<(i64, String)>, DbExecutorError> {
+    let query = team_groups::table
+        .inner_join(access_groups::table.inner_join(access_group_members::table))
+        .filter(access_group_members::user_id.eq(usr_id))
+        .select((team_groups::team_id, team_groups::role));
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<(i64, String)>,
+    };
+    Ok(req.state().rdb.send(sel).wait()??)
+}
+
+/// Ids of the teams the user is a member of, in any role
+pub fn team_ids(req: &HttpRequest<AppState>, usr_id: i64) -> Result<Vec<i64>, DbExecutorError> {
+    let mut ids: Vec<i64> = memberships(req, usr_id)?
+        .into_iter()
+        .map(|(org_id, _)| org_id)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// The role of the user in each of their teams, the most powerful one should there be several
+pub fn roles(
+    req: &HttpRequest<AppState>,
+    usr_id: i64,
+) -> Result<HashMap<i64, Role>, DbExecutorError> {
+    let mut res = HashMap::new();
+    let memberships = memberships(req, usr_id)?;
+    for role in Role::all().into_iter().rev() {
+        for (org_id, _) in memberships.iter().filter(|(_, name)| name == role.as_str()) {
+            res.insert(*org_id, role);
+        }
+    }
+    Ok(res)
+}
+
+/// The role of the user in the team, the most powerful one should there be several
+pub fn role_of(req: &HttpRequest<AppState>, org_id: i64, usr_id: i64) -> Option<Role> {
+    let roles: Vec<Role> = memberships(req, usr_id)
+        .ok()?
+        .into_iter()
+        .filter(|(tid, _)| *tid == org_id)
+        .filter_map(|(_, role)| Role::parse(&role))
+        .collect();
+    Role::all().into_iter().find(|role| roles.contains(role))
+}
+
+/// Everyone who is a member of the team, owners first
+pub fn members(req: &HttpRequest<AppState>, org_id: i64) -> Result<Vec<Member>, DbExecutorError> {
+    let query = team_groups::table
+        .inner_join(access_groups::table.inner_join(access_group_members::table))
+        .filter(team_groups::team_id.eq(org_id))
+        .select((access_group_members::user_id, team_groups::role));
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<(i64, String)>,
+    };
+    let roles = req.state().rdb.send(sel).wait()??;
+    let ids: Vec<i64> = roles.iter().map(|(usr_id, _)| *usr_id).collect();
+    let query = user_meta::table.filter(user_meta::user_id.eq_any(ids));
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<UserMeta>,
+    };
+    let users = req.state().rdb.send(sel).wait()??;
+    let mut res = Vec::new();
+    for role in Role::all() {
+        for (usr_id, _) in roles.iter().filter(|(_, r)| r == role.as_str()) {
+            if let Some(user) = users.iter().find(|u| u.user_id == *usr_id) {
+                res.push(Member {
+                    user: user.clone(),
+                    role,
+                });
+            }
+        }
+    }
+    Ok(res)
+}
+
+fn group_of(conn: &PgConnection, org_id: i64, role: Role) -> QueryResult<TeamGroup> {
+    team_groups::table
+        .filter(team_groups::team_id.eq(org_id))
+        .filter(team_groups::role.eq(role.as_str()))
+        .first(conn)
+}
+
+/// Drops the user from every role group of the team
+pub fn remove(conn: &PgConnection, org_id: i64, usr_id: i64) -> QueryResult<usize> {
+    let groups = team_groups::table
+        .filter(team_groups::team_id.eq(org_id))
+        .select(team_groups::access_group_id);
+    diesel::delete(
+        access_group_members::table
+            .filter(access_group_members::user_id.eq(usr_id))
+            .filter(access_group_members::access_group_id.eq_any(groups)),
+    )
+    .execute(conn)
+}
+
+/// Makes the user a member of the team in exactly one role
+pub fn set_role(
+    conn: &PgConnection,
+    org_id: i64,
+    usr_id: i64,
+    role: Role,
+    actor: &str,
+) -> QueryResult<()> {
+    use diesel::insert_into;
+    let group = group_of(conn, org_id, role)?;
+    remove(conn, org_id, usr_id)?;
+    let acc: i64 = insert_into(access_control::table)
+        .values((
+            access_control::created_by.eq(actor),
+            access_control::updated_by.eq(actor),
+        ))
+        .returning(access_control::id)
+        .get_result(conn)?;
+    insert_into(access_group_members::table)
+        .values((
+            access_group_members::access_group_id.eq(group.access_group_id),
+            access_group_members::user_id.eq(usr_id),
+            access_group_members::access_control_id.eq(acc),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Hands the team over to another member, the previous owner stays on as admin
+pub fn transfer_owner(
+    conn: &PgConnection,
+    org: &Team,
+    new_owner: i64,
+    actor: &str,
+) -> QueryResult<()> {
+    conn.transaction(|| {
+        set_role(conn, org.id, org.user_id, Role::Admin, actor)?;
+        set_role(conn, org.id, new_owner, Role::Owner, actor)?;
+        diesel::update(teams::table.filter(teams::id.eq(org.id)))
+            .set(teams::user_id.eq(new_owner))
+            .execute(conn)?;
+        Ok(())
+    })
+}
@@ -2,29 +2,51 @@ use actix_web::middleware::session::RequestSession;
 use actix_web::{Error, HttpRequest, HttpResponse};
 
 use crate::db::AppState;
+use crate::modules::team::data::Team;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
 use crate::render::Failure;
 use crate::utils::http_ok;
 
 use crate::menu::default_menu;
 use crate::modules::meta::default_meta;
 
+/// Lists the teams of the user, so they can switch between them without logging out
 pub fn select(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
-    debug!("{:?}", req);
-    let _res = req.session().set("org", 1);
-    http_ok(select_render())
+    let usr_meta = match UserMeta::current(req) {
+        Ok(usr_meta) => usr_meta,
+        Err(_) => {
+            return Ok(HttpResponse::Found()
+                .header("location", "/user/login")
+                .finish())
+        }
+    };
+    let orgs = super::for_user(req, usr_meta.user_id).unwrap_or_default();
+    let current = super::current(req)?;
+    if current.is_none() && orgs.len() == 1 {
+        req.session().set("org", orgs[0].id)?;
+        return Ok(HttpResponse::Found()
+            .header("location", "/project/list")
+            .finish());
+    }
+    http_ok(select_render(&orgs, current, &req.impersonating()))
 }
 
-fn select_render() -> Result<String, Failure> {
-    debug!("zazaza");
+fn select_render(
+    orgs: &[Team],
+    current: Option<i64>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let meta = default_meta("Select Team");
     let links = default_menu();
-    let cnt = ructe_block_res!(crate::templates::team::selector)?;
+    let cnt = ructe_block_res!(crate::templates::team::selector, orgs, current)?;
     ructe_page_res!(
         crate::templates::navigation::frame,
         meta,
         &toplinks,
         &links,
-        &cnt
+        &cnt,
+        impersonating
     )
 }
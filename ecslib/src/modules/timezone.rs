@@ -0,0 +1,175 @@
+use actix_web::HttpRequest;
+
+use chrono::offset::LocalResult;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use validator::ValidationError;
+
+use crate::db::AppState;
+use crate::modules::user::UserMeta;
+
+/// Local datetimes as sent by `datetime-local` inputs and typed by hand
+const LOCAL_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+];
+
+/// The timezone with the given IANA name, `None` for empty or unknown names
+pub fn parse_name(name: &str) -> Option<Tz> {
+    name.trim().parse::<Tz>().ok()
+}
+
+/// Form validation of a timezone name, an empty name keeps the default timezone
+pub fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() || parse_name(name).is_some() {
+        return Ok(());
+    }
+    let mut err = ValidationError::new("timezone");
+    err.message = Some("Not a known timezone, use a name like Europe/Budapest".into());
+    Err(err)
+}
+
+/// The timezone of the user, or of the selected team when the user has none, UTC otherwise
+pub fn current(req: &HttpRequest<AppState>) -> Tz {
+    if let Ok(usr_meta) = UserMeta::current(req) {
+        if let Some(tz) = usr_meta.timezone.as_ref().and_then(|name| parse_name(name)) {
+            return tz;
+        }
+    }
+    if let Ok(Some(org_id)) = crate::modules::team::current(req) {
+        if let Ok(org) = crate::modules::team::load(req, org_id) {
+            if let Some(tz) = org.timezone.as_ref().and_then(|name| parse_name(name)) {
+                return tz;
+            }
+        }
+    }
+    Tz::UTC
+}
+
+fn from_local(local: &NaiveDateTime, tz: Tz) -> Result<DateTime<Utc>, String> {
+    match tz.from_local_datetime(local) {
+        LocalResult::Single(dt) => Ok(dt.with_timezone(&Utc)),
+        // the hour repeated when the clocks go back, the first one is meant
+        LocalResult::Ambiguous(first, _) => Ok(first.with_timezone(&Utc)),
+        LocalResult::None => Err(format!("{} does not exist in {}", local, tz.name())),
+    }
+}
+
+/// Reads a date entered in a form, `Ok(None)` when left empty.
+/// RFC 3339 timestamps carry their own offset, plain dates are the start of the day and
+/// local datetimes the time in `tz`. Anything else is an error with a message for the form.
+pub fn parse_input(input: &str, tz: Tz) -> Result<Option<DateTime<Utc>>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(Some(dt.with_timezone(&Utc)));
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return from_local(&day.and_hms(0, 0, 0), tz).map(Some);
+    }
+    for fmt in LOCAL_FORMATS {
+        if let Ok(local) = NaiveDateTime::parse_from_str(input, fmt) {
+            return from_local(&local, tz).map(Some);
+        }
+    }
+    Err(format!(
+        "Not a date, use YYYY-MM-DD or YYYY-MM-DD HH:MM, got {}",
+        input
+    ))
+}
+
+/// The day of `dt` in `tz`, as shown in lists
+pub fn format_date(dt: &DateTime<Utc>, tz: Tz) -> String {
+    dt.with_timezone(&tz).format("%Y-%m-%d").to_string()
+}
+
+/// The time of `dt` in `tz` to the minute
+pub fn format_datetime(dt: &DateTime<Utc>, tz: Tz) -> String {
+    dt.with_timezone(&tz).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// The time of `dt` in `tz` as `datetime-local` inputs take it
+pub fn format_input(dt: &DateTime<Utc>, tz: Tz) -> String {
+    dt.with_timezone(&tz).format("%Y-%m-%dT%H:%M").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_input, validate_name};
+    use chrono::{DateTime, Utc};
+    use chrono_tz::Tz;
+
+    fn utc(rfc3339: &str) -> Option<DateTime<Utc>> {
+        Some(
+            DateTime::parse_from_rfc3339(rfc3339)
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
+    #[test]
+    fn local_times() {
+        let budapest: Tz = "Europe/Budapest".parse().unwrap();
+        let cases = [
+            ("", None),
+            ("2019-01-15", utc("2019-01-14T23:00:00Z")),
+            ("2019-07-15 14:30", utc("2019-07-15T12:30:00Z")),
+            ("2019-07-15T14:30", utc("2019-07-15T12:30:00Z")),
+            ("2019-07-15 14:30:15", utc("2019-07-15T12:30:15Z")),
+            ("2019-07-15T14:30:00+05:00", utc("2019-07-15T09:30:00Z")),
+            // the hour before the clocks go back to winter time
+            ("2019-10-27 01:59", utc("2019-10-26T23:59:00Z")),
+            // the hour after the clocks went forward
+            ("2019-03-31 03:00", utc("2019-03-31T01:00:00Z")),
+        ];
+        for (input, expected) in cases.iter() {
+            assert_eq!(parse_input(input, budapest), Ok(*expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn ambiguous_times_are_the_first() {
+        let budapest: Tz = "Europe/Budapest".parse().unwrap();
+        // 02:30 is passed twice on 2019-10-27, first in summer time (+02:00)
+        assert_eq!(
+            parse_input("2019-10-27 02:30", budapest),
+            Ok(utc("2019-10-27T00:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn skipped_times_are_refused() {
+        let budapest: Tz = "Europe/Budapest".parse().unwrap();
+        let sao_paulo: Tz = "America/Sao_Paulo".parse().unwrap();
+        let cases = [
+            // the clocks jump from 02:00 to 03:00
+            ("2019-03-31 02:30", budapest),
+            ("2019-03-31T02:00", budapest),
+            // the clocks jumped from midnight to 01:00, the day has no start
+            ("2018-11-04", sao_paulo),
+        ];
+        for (input, tz) in cases.iter() {
+            let res = parse_input(input, *tz);
+            assert!(res.is_err(), "{} in {}: {:?}", input, tz.name(), res);
+            assert!(res.unwrap_err().contains("does not exist"));
+        }
+    }
+
+    #[test]
+    fn malformed_input() {
+        for input in ["tomorrow", "2019-13-01", "2019-02-30 10:00", "15/07/2019"].iter() {
+            assert!(parse_input(input, Tz::UTC).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert!(validate_name("").is_ok());
+        assert!(validate_name("Europe/Budapest").is_ok());
+        assert!(validate_name("Mars/Olympus").is_err());
+    }
+}
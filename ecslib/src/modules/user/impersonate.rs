@@ -0,0 +1,253 @@
+use actix_web::http::Method;
+use actix_web::middleware::identity::RequestIdentity;
+use actix_web::middleware::session::RequestSession;
+use actix_web::middleware::{Middleware, Started};
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use chrono::Utc;
+use diesel::prelude::*;
+use futures::future::Future;
+use std::marker::PhantomData;
+
+use crate::db::{AppState, SQuery};
+use crate::modules::access::is_admin;
+use crate::modules::meta::default_meta;
+use crate::modules::user::UserMeta;
+use crate::render::Failure;
+use crate::utils::http_ok;
+
+/// Access keys with this `key` allow their holder to act as another user
+pub const IMPERSONATION_KEY: &str = "impersonate";
+/// Session entry holding the e-mail of the impersonated user
+const SESSION_KEY: &str = "impersonate";
+/// Paths which are never served while impersonating, regardless of the method
+const PROTECTED_PATHS: &[&str] = &["/user/settings", "/user/impersonate/start"];
+/// The only account change allowed while impersonating
+const STOP_PATH: &str = "/user/impersonate/stop";
+
+pub trait RequestImpersonation {
+    /// The e-mail of the user being impersonated, if any
+    fn impersonating(&self) -> Option<String>;
+    /// The identity the request acts as: the impersonated user, or the logged in one
+    fn effective_identity(&self) -> Option<String>;
+}
+impl<S> RequestImpersonation for HttpRequest<S> {
+    fn impersonating(&self) -> Option<String> {
+        self.identity()?;
+        self.session().get::<String>(SESSION_KEY).ok()?
+    }
+    fn effective_identity(&self) -> Option<String> {
+        self.impersonating().or_else(|| self.identity())
+    }
+}
+
+/// Whether the logged in user may act as other users
+pub fn can_impersonate(req: &HttpRequest<AppState>, usr_id: i64) -> bool {
+    if is_admin(req, usr_id) {
+        return true;
+    }
+    use crate::schema::access_keys::dsl::*;
+    let query = access_keys
+        .filter(user_id.eq(usr_id))
+        .filter(key.eq(IMPERSONATION_KEY))
+        .filter(expiry.gt(Utc::now()))
+        .select(id);
+    let sel = SQuery {
+        select: query,
+        phantom: PhantomData::<i64>,
+    };
+    match req.state().rdb.send(sel).wait() {
+        Ok(Ok(keys)) => !keys.is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether `actor` may act as `target`. Privileged users, admins and other impersonators, are
+/// never a target: `PermissionCheck` would hand their grants to the actor.
+fn may_act_as<F>(actor: i64, target: i64, privileged: F) -> bool
+where
+    F: Fn(i64) -> bool,
+{
+    actor != target && !privileged(target)
+}
+
+fn real_user(req: &HttpRequest<AppState>) -> Option<UserMeta> {
+    let mail = req.identity()?;
+    UserMeta::load(req, mail).ok()
+}
+
+fn index_render(impersonating: &Option<String>) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let cnt = ructe_block_res!(crate::templates::user::impersonate, impersonating)?;
+    let meta = default_meta("Act as user");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    match real_user(req) {
+        Some(usr_meta) if can_impersonate(req, usr_meta.user_id) => {
+            http_ok(index_render(&req.impersonating()))
+        }
+        _ => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImpersonateParams {
+    email: String,
+}
+pub fn start((req, form): (HttpRequest<AppState>, Form<ImpersonateParams>)) -> HttpResponse {
+    if let Some(usr_meta) = real_user(&req) {
+        if can_impersonate(&req, usr_meta.user_id) {
+            if let Ok(target) = UserMeta::load(&req, form.email.trim().to_owned()) {
+                if target.user_id != usr_meta.user_id {
+                    // admins can always impersonate, so this covers them as well
+                    if !may_act_as(usr_meta.user_id, target.user_id, |usr_id| {
+                        can_impersonate(&req, usr_id)
+                    }) {
+                        warn!(
+                            "Impersonation refused: {} tried to act as privileged {}",
+                            usr_meta.email, target.email
+                        );
+                        return HttpResponse::Forbidden().finish();
+                    }
+                    if req
+                        .session()
+                        .set(SESSION_KEY, target.email.clone())
+                        .is_err()
+                    {
+                        return HttpResponse::ExpectationFailed().finish();
+                    }
+                    info!(
+                        "Impersonation started: {} acts as {}",
+                        usr_meta.email, target.email
+                    );
+                    return HttpResponse::Found()
+                        .header("location", "/project/list")
+                        .finish();
+                }
+            }
+            return HttpResponse::Found()
+                .header("location", "/user/impersonate")
+                .finish();
+        }
+    }
+    HttpResponse::Forbidden().finish()
+}
+
+pub fn stop(req: &HttpRequest<AppState>) -> HttpResponse {
+    if let Some(target) = req.impersonating() {
+        info!(
+            "Impersonation stopped: {:?} acted as {}",
+            req.identity(),
+            target
+        );
+        req.session().remove(SESSION_KEY);
+    }
+    HttpResponse::Found()
+        .header("location", "/user/impersonate")
+        .finish()
+}
+
+/// Whether a request is refused while impersonating: the account of the customer is left
+/// alone, nothing under `/user` is changed beside stopping the impersonation
+fn refused_while_impersonating(method: &Method, path: &str) -> bool {
+    if PROTECTED_PATHS.iter().any(|prot| path.starts_with(prot)) {
+        return true;
+    }
+    let account = path == "/user" || path.starts_with("/user/");
+    account && method != Method::GET && method != Method::HEAD && !path.starts_with(STOP_PATH)
+}
+
+/// Logs every request made under impersonation, and refuses credential changes
+pub struct ImpersonationGuard;
+impl<S> Middleware<S> for ImpersonationGuard {
+    fn start(&self, req: &HttpRequest<S>) -> actix_web::Result<Started> {
+        if let Some(target) = req.impersonating() {
+            info!(
+                "Impersonated request: {:?} as {} {} {}",
+                req.identity(),
+                target,
+                req.method(),
+                req.path()
+            );
+            if refused_while_impersonating(req.method(), req.path()) {
+                return Ok(Started::Response(HttpResponse::Forbidden().finish()));
+            }
+        }
+        Ok(Started::Done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{may_act_as, refused_while_impersonating};
+    use actix_web::http::Method;
+
+    // user 1 is the impersonator, 2 an ordinary user, 3 an admin and 4 another impersonator
+    fn privileged(usr_id: i64) -> bool {
+        usr_id == 1 || usr_id == 3 || usr_id == 4
+    }
+
+    #[test]
+    fn ordinary_users_can_be_impersonated() {
+        assert!(may_act_as(1, 2, privileged));
+    }
+
+    #[test]
+    fn admins_and_impersonators_are_refused() {
+        assert!(!may_act_as(1, 3, privileged));
+        assert!(!may_act_as(1, 4, privileged));
+    }
+
+    #[test]
+    fn acting_as_yourself_is_refused() {
+        assert!(!may_act_as(1, 1, privileged));
+        assert!(!may_act_as(2, 2, |_| false));
+    }
+
+    #[test]
+    fn the_account_is_left_alone() {
+        assert!(refused_while_impersonating(&Method::GET, "/user/settings"));
+        assert!(refused_while_impersonating(&Method::POST, "/user/settings"));
+        assert!(refused_while_impersonating(
+            &Method::POST,
+            "/user/impersonate/start"
+        ));
+        assert!(refused_while_impersonating(&Method::POST, "/user/register"));
+        assert!(refused_while_impersonating(
+            &Method::DELETE,
+            "/user/anything"
+        ));
+    }
+
+    #[test]
+    fn stopping_and_browsing_are_served() {
+        assert!(!refused_while_impersonating(
+            &Method::GET,
+            "/user/impersonate/stop"
+        ));
+        assert!(!refused_while_impersonating(
+            &Method::POST,
+            "/user/impersonate/stop"
+        ));
+        assert!(!refused_while_impersonating(
+            &Method::GET,
+            "/user/impersonate"
+        ));
+        assert!(!refused_while_impersonating(&Method::GET, "/user/logout"));
+        assert!(!refused_while_impersonating(
+            &Method::POST,
+            "/project/1/todo"
+        ));
+        assert!(!refused_while_impersonating(&Method::POST, "/username"));
+    }
+}
@@ -8,18 +8,20 @@ use std::marker::PhantomData;
 
 use crate::db::{AppState, SQuery};
 use crate::modules::navigation::{Link, ListContext, Listing, Permission};
+use crate::modules::user::impersonate::RequestImpersonation;
 use crate::modules::user::UserMeta;
 use crate::render::Failure;
 use crate::schema::user_meta::dsl::*;
 use crate::utils::http_ok;
 
-fn index_render(data: Vec<Listing>) -> Result<String, Failure> {
+fn index_render(data: Vec<Listing>, impersonating: &Option<String>) -> Result<String, Failure> {
     let toplinks = crate::menu::default_top_menu();
     let links = crate::menu::default_menu();
     let ctx = ListContext {
         title: "User".to_string(),
         head: "List of users".to_string(),
         search: false,
+        sort: None,
     };
     let perm = crate::modules::navigation::PermissionSet {
         browse: true,
@@ -35,7 +37,8 @@ fn index_render(data: Vec<Listing>) -> Result<String, Failure> {
         meta,
         &toplinks,
         &links,
-        &list
+        &list,
+        impersonating
     )
 }
 
@@ -56,7 +59,7 @@ pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
         .unwrap()
         .unwrap();
     let usr_list = create_listing(&usr_metas);
-    http_ok(index_render(usr_list))
+    http_ok(index_render(usr_list, &req.impersonating()))
 }
 
 fn create_listing(metas: &[UserMeta]) -> Vec<Listing> {
@@ -1,4 +1,5 @@
 use actix_web::middleware::identity::RequestIdentity;
+use actix_web::middleware::session::RequestSession;
 use actix_web::{Error, Form, HttpRequest, HttpResponse};
 use argon2rs::{Argon2, Variant};
 use diesel::prelude::*;
@@ -15,9 +16,6 @@ use crate::modules::navigation::Link;
 use crate::modules::user::{UserMeta, UserPwd};
 use crate::schema::user_pwd::dsl::*;
 
-// TODO: remove once not needed
-use actix_web::middleware::session::RequestSession;
-
 fn index_render() -> Result<String, Failure> {
     let mut links = crate::modules::navigation::default_menu();
     let register = Link::new("Register", "/user/register");
@@ -80,9 +78,6 @@ pub fn login((req, form): (HttpRequest<AppState>, Form<LoginParams>)) -> HttpRes
             debug!("stored_hash: {}", stored_hash.clone());
             if calc_hash == stored_hash {
                 req.remember(form.email.clone());
-
-                // TODO: remove once not needed
-                let _res = req.session().set("org", 1);
                 info!("Login successfull {}", form.email);
 
                 return HttpResponse::Found()
@@ -102,6 +97,7 @@ pub fn login((req, form): (HttpRequest<AppState>, Form<LoginParams>)) -> HttpRes
 }
 pub fn logout(req: &HttpRequest<AppState>) -> HttpResponse {
     debug!("Handling logout request: {:?}", req);
+    req.session().clear();
     req.forget();
     HttpResponse::Found()
         .header("location", "/user/login")
@@ -1,9 +1,11 @@
 #![allow(proc_macro_derive_resolution_fallback)]
 
+pub mod impersonate;
 pub mod list;
 pub mod login;
 pub mod register;
 pub mod restrict;
+pub mod settings;
 pub mod token;
 
 use crate::db::{AppState, SQuery};
@@ -32,6 +34,7 @@ pub struct UserMeta {
     pub phone: String,
     pub frozen: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub timezone: Option<String>,
 }
 
 #[derive(Insertable, Queryable, Associations, Debug, Serialize, Deserialize)]
@@ -78,4 +81,13 @@ impl UserMeta {
         }
         Err(UserLoadError::NoSuchUserError)
     }
+
+    /// Loads the user the request acts as, see `RequestImpersonation::effective_identity`
+    pub fn current(req: &HttpRequest<AppState>) -> Result<UserMeta, UserLoadError> {
+        use crate::modules::user::impersonate::RequestImpersonation;
+        match req.effective_identity() {
+            Some(mail) => UserMeta::load(req, mail),
+            None => Err(UserLoadError::NoSuchUserError),
+        }
+    }
 }
@@ -0,0 +1,172 @@
+use actix_web::{Error, Form, HttpRequest, HttpResponse};
+
+use diesel::prelude::*;
+use futures::future::Future;
+use std::marker::PhantomData;
+use validator::Validate;
+
+use crate::db::{AppState, WQuery};
+use crate::modules::meta::default_meta;
+use crate::modules::navigation::{EditableField, InputType, ListContext, PermissionSet};
+use crate::modules::timezone::validate_name as validate_timezone;
+use crate::modules::user::impersonate::RequestImpersonation;
+use crate::modules::user::UserMeta;
+use crate::render::Failure;
+use crate::utils::http_ok;
+
+/// The settings a user changes for themselves
+#[derive(Deserialize, Serialize, Debug, Clone, Default, Validate)]
+pub struct SettingsData {
+    #[serde(default)]
+    #[validate(custom = "validate_timezone")]
+    pub timezone: String,
+}
+impl SettingsData {
+    /// The IANA name of the timezone, `None` when left empty to follow the team
+    pub fn timezone(&self) -> Option<String> {
+        let name = self.timezone.trim();
+        if name.is_empty() {
+            None
+        } else {
+            Some(name.to_owned())
+        }
+    }
+
+    /// Why the timezone is refused, if it is
+    pub fn timezone_error(&self) -> Option<String> {
+        let errs = self.validate().err()?;
+        let field_errs = errs.field_errors();
+        let err = field_errs.get("timezone")?.first()?;
+        Some(
+            err.message
+                .as_ref()
+                .map(|msg| msg.to_string())
+                .unwrap_or_else(|| "Invalid value".to_string()),
+        )
+    }
+
+    fn fields(&self, error: Option<String>) -> Vec<EditableField> {
+        vec![EditableField {
+            input_type: InputType::Input,
+            title: "Timezone".to_string(),
+            name: "timezone".to_string(),
+            value: self.timezone.clone(),
+            links: Vec::new(),
+            required: false,
+            error,
+        }]
+    }
+}
+
+impl<'a> From<&'a UserMeta> for SettingsData {
+    fn from(usr_meta: &'a UserMeta) -> Self {
+        SettingsData {
+            timezone: usr_meta.timezone.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Everyone reads and edits their own settings
+fn own() -> PermissionSet {
+    let mut perm = PermissionSet::deny();
+    perm.grant("read");
+    perm.grant("edit");
+    perm
+}
+
+fn index_render(
+    fields: Vec<EditableField>,
+    impersonating: &Option<String>,
+) -> Result<String, Failure> {
+    let toplinks = crate::menu::default_top_menu();
+    let links = crate::menu::default_menu();
+    let ctx = ListContext {
+        title: "Settings".to_string(),
+        head: "My Settings".to_string(),
+        search: false,
+        sort: None,
+    };
+    let cnt = ructe_block_res!(crate::templates::navigation::edit, &fields, &ctx, &own())?;
+    let meta = default_meta("My Settings");
+    ructe_page_res!(
+        crate::templates::navigation::frame,
+        meta,
+        &toplinks,
+        &links,
+        &cnt,
+        impersonating
+    )
+}
+
+pub fn index(req: &HttpRequest<AppState>) -> Result<HttpResponse, Error> {
+    match UserMeta::current(req) {
+        Ok(usr_meta) => {
+            let fields = SettingsData::from(&usr_meta).fields(None);
+            http_ok(index_render(fields, &req.impersonating()))
+        }
+        Err(_) => Ok(HttpResponse::Forbidden().finish()),
+    }
+}
+
+pub fn save(
+    (req, form): (HttpRequest<AppState>, Form<SettingsData>),
+) -> Result<HttpResponse, Error> {
+    let usr_meta = match UserMeta::current(&req) {
+        Ok(usr_meta) => usr_meta,
+        Err(_) => return Ok(HttpResponse::Forbidden().finish()),
+    };
+    if let Some(err) = form.timezone_error() {
+        return http_ok(index_render(form.fields(Some(err)), &req.impersonating()));
+    }
+    use crate::schema::user_meta::dsl::*;
+    let target = user_meta.filter(user_id.eq(usr_meta.user_id));
+    let query = diesel::update(target).set(timezone.eq(form.timezone()));
+    let upd = WQuery {
+        query,
+        phantom: PhantomData::<UserMeta>,
+    };
+    match req.state().wdb.send(upd).wait() {
+        Ok(Ok(_)) => Ok(HttpResponse::Found()
+            .header("location", "/user/settings")
+            .finish()),
+        res => {
+            error!(
+                "Saving the settings of {} failed: {:?}",
+                usr_meta.email, res
+            );
+            Ok(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SettingsData;
+
+    fn settings(timezone: &str) -> SettingsData {
+        SettingsData {
+            timezone: timezone.to_string(),
+        }
+    }
+
+    #[test]
+    fn timezone_names() {
+        let cases = [
+            ("", None, false),
+            ("  ", None, false),
+            ("Europe/Budapest", Some("Europe/Budapest"), false),
+            (" America/New_York ", Some("America/New_York"), false),
+            ("Mars/Olympus", Some("Mars/Olympus"), true),
+        ];
+        for (input, stored, refused) in cases.iter() {
+            let data = settings(input);
+            assert_eq!(
+                data.timezone().as_ref().map(String::as_str),
+                *stored,
+                "{}",
+                input
+            );
+            assert_eq!(data.timezone_error().is_some(), *refused, "{}", input);
+        }
+    }
+}
@@ -1,3 +1,11 @@
+use actix_web::error::ErrorInternalServerError;
+use actix_web::HttpRequest;
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use futures::future::Future;
+
+use crate::db::{AppState, Conn};
+
 #[macro_use]
 macro_rules! http_ok {
     ($html:expr) => {
@@ -17,3 +25,30 @@ pub fn http_ok(
         Err(_) => http_ok!("Render Failure"),
     }
 }
+
+/// A pooled connection of the writer, for handlers running their own queries and transactions
+pub type WriteConn = PooledConnection<ConnectionManager<PgConnection>>;
+
+/// Checks a connection out of the writer. A failing mailbox or pool answers the request with a
+/// 500 instead of taking the worker down.
+pub fn write_conn(req: &HttpRequest<AppState>) -> Result<WriteConn, actix_web::Error> {
+    match req.state().wdb.send(Conn {}).wait() {
+        Ok(Ok(conn)) => Ok(conn),
+        Ok(Err(e)) => {
+            error!("No write connection: {:?}", e);
+            Err(ErrorInternalServerError("database unavailable"))
+        }
+        Err(e) => {
+            error!("Write executor unreachable: {:?}", e);
+            Err(ErrorInternalServerError("database unavailable"))
+        }
+    }
+}
+
+/// Renders Markdown written by users, with everything but safe markup removed
+pub fn markdown_html(text: &str) -> String {
+    use pulldown_cmark::{html, Parser};
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new(text));
+    ammonia::clean(&unsafe_html)
+}
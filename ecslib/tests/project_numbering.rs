@@ -0,0 +1,86 @@
+//! Needs a migrated database with at least one team, run it with
+//! > cargo test -p ecslib --test project_numbering -- --ignored
+
+use std::collections::HashSet;
+use std::env;
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool};
+
+use ecslib::modules::project::next_projectid;
+use ecspg::schema::{projects, teams};
+
+const ADDS: usize = 32;
+const TEST_TITLE: &str = "concurrent numbering test";
+
+fn pool() -> Pool<ConnectionManager<PgConnection>> {
+    dotenv::dotenv().ok();
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    Pool::builder()
+        .max_size(ADDS as u32)
+        .build(ConnectionManager::new(database_url))
+        .expect("Error building the connection pool")
+}
+
+/// Allocates a number and inserts a project with it, the way `project::add::save` does
+fn add_project(conn: &PgConnection, org_id: i64) -> QueryResult<i64> {
+    conn.transaction(|| {
+        let number = next_projectid(conn, org_id)?;
+        diesel::insert_into(projects::table)
+            .values((
+                projects::projectid.eq(number),
+                projects::team_id.eq(org_id),
+                projects::uuid.eq(uuid::Uuid::new_v4()),
+                projects::title.eq(TEST_TITLE),
+                projects::content.eq(""),
+            ))
+            .execute(conn)?;
+        Ok(number)
+    })
+}
+
+#[test]
+#[ignore]
+fn concurrent_adds_get_distinct_numbers() {
+    let pool = pool();
+    let org_id: i64 = teams::table
+        .select(teams::id)
+        .order(teams::id.asc())
+        .first(&pool.get().unwrap())
+        .expect("The test needs a team in the database");
+
+    let barrier = Arc::new(Barrier::new(ADDS));
+    let workers: Vec<_> = (0..ADDS)
+        .map(|_| {
+            let pool = pool.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let conn = pool.get().unwrap();
+                barrier.wait();
+                add_project(&conn, org_id)
+            })
+        })
+        .collect();
+    let results: Vec<QueryResult<i64>> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+
+    let conn = pool.get().unwrap();
+    diesel::delete(
+        projects::table
+            .filter(projects::team_id.eq(org_id))
+            .filter(projects::title.eq(TEST_TITLE)),
+    )
+    .execute(&conn)
+    .unwrap();
+
+    let numbers: Vec<i64> = results
+        .into_iter()
+        .map(|res| res.expect("Adding a project failed"))
+        .collect();
+    let distinct: HashSet<i64> = numbers.iter().cloned().collect();
+    assert_eq!(distinct.len(), ADDS, "duplicate numbers: {:?}", numbers);
+    let lowest = *numbers.iter().min().unwrap();
+    assert_eq!(*numbers.iter().max().unwrap(), lowest + ADDS as i64 - 1);
+}
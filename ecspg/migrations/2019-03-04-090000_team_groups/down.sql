@@ -0,0 +1,6 @@
+CREATE TEMPORARY TABLE dropped_groups AS SELECT access_group_id FROM team_groups;
+DROP TABLE team_groups;
+DELETE FROM access_rules WHERE access_group_id IN (SELECT access_group_id FROM dropped_groups);
+DELETE FROM access_group_members WHERE access_group_id IN (SELECT access_group_id FROM dropped_groups);
+DELETE FROM access_groups WHERE id IN (SELECT access_group_id FROM dropped_groups);
+DROP TABLE dropped_groups;
@@ -0,0 +1,43 @@
+-- Team membership: every team has one access group per role,
+-- being a member of a team means being a member of one of its role groups.
+-- The rules of a role group act on the access control entry of the team,
+-- which also governs the projects of the team.
+CREATE TABLE team_groups (
+  id SERIAL8 PRIMARY KEY,
+  team_id INT8 NOT NULL REFERENCES teams(id),
+  access_group_id INT8 NOT NULL REFERENCES access_groups(id),
+  role TEXT NOT NULL CHECK(role IN ('owner', 'admin', 'member', 'viewer')),
+  UNIQUE (team_id, role)
+);
+GRANT SELECT ON team_groups TO ecs_read;
+GRANT SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES ON team_groups TO ecs_write;
+GRANT ALL PRIVILEGES ON ALL SEQUENCES IN SCHEMA public TO ecs_write;
+
+-- Existing teams get their role groups, their owning user becomes the member of the owner group
+DO $$
+DECLARE
+  tm RECORD;
+  rl TEXT;
+  acc INT8;
+  grp INT8;
+BEGIN
+  FOR tm IN SELECT id, access_control_id, user_id FROM teams LOOP
+    FOREACH rl IN ARRAY ARRAY['owner', 'admin', 'member', 'viewer'] LOOP
+      INSERT INTO access_control (created_by, updated_by)
+        VALUES ('migration', 'migration') RETURNING id INTO acc;
+      INSERT INTO access_groups (name, access_control_id)
+        VALUES ('team:' || tm.id || ':' || rl, acc) RETURNING id INTO grp;
+      INSERT INTO team_groups (team_id, access_group_id, role) VALUES (tm.id, grp, rl);
+      INSERT INTO access_rules (access_group_id, access_control_id, access_type)
+        SELECT grp, tm.access_control_id, tp FROM unnest(CASE rl
+          WHEN 'member' THEN ARRAY['browse', 'read', 'edit', 'add']
+          WHEN 'viewer' THEN ARRAY['browse', 'read']
+          ELSE ARRAY['browse', 'read', 'edit', 'add', 'delete']
+        END) AS tp;
+      IF rl = 'owner' THEN
+        INSERT INTO access_group_members (access_group_id, user_id, access_control_id)
+          VALUES (grp, tm.user_id, acc);
+      END IF;
+    END LOOP;
+  END LOOP;
+END $$;
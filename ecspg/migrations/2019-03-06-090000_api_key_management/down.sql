@@ -0,0 +1,11 @@
+DROP INDEX api_keys_team_id;
+DROP INDEX api_keys_key_hash;
+ALTER TABLE api_keys
+  DROP COLUMN name,
+  DROP COLUMN prefix,
+  DROP COLUMN scope,
+  DROP COLUMN created_at,
+  DROP COLUMN expires_at,
+  DROP COLUMN last_used_at,
+  DROP COLUMN revoked_at;
+ALTER TABLE api_keys RENAME COLUMN key_hash TO api_key;
@@ -0,0 +1,19 @@
+-- Api keys are named, scoped and stored as a hash, the key itself is only shown once.
+-- `prefix` is the not secret start of the key, so a key can be recognised in the list.
+ALTER TABLE api_keys RENAME COLUMN api_key TO key_hash;
+ALTER TABLE api_keys
+  ADD COLUMN name TEXT NOT NULL DEFAULT 'Legacy key',
+  ADD COLUMN prefix TEXT NOT NULL DEFAULT '',
+  ADD COLUMN scope TEXT NOT NULL DEFAULT 'read' CHECK(scope IN ('read', 'toggle', 'full')),
+  ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  ADD COLUMN expires_at TIMESTAMPTZ,
+  ADD COLUMN last_used_at TIMESTAMPTZ,
+  ADD COLUMN revoked_at TIMESTAMPTZ;
+ALTER TABLE api_keys ALTER COLUMN name DROP DEFAULT;
+ALTER TABLE api_keys ALTER COLUMN prefix DROP DEFAULT;
+
+-- Existing keys were stored in plaintext and never shown to anyone, they can't be in use
+UPDATE api_keys SET key_hash = 'legacy:' || id, revoked_at = now();
+
+CREATE UNIQUE INDEX api_keys_key_hash ON api_keys(key_hash);
+CREATE INDEX api_keys_team_id ON api_keys(team_id);
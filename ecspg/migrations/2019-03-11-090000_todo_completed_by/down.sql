@@ -0,0 +1,4 @@
+DROP INDEX projects_team_id;
+DROP INDEX todos_project_id;
+DROP INDEX todos_completed_by;
+ALTER TABLE todos DROP COLUMN completed_by;
@@ -0,0 +1,5 @@
+-- Who completed a todo, for the contributor statistics of the team dashboard
+ALTER TABLE todos ADD COLUMN completed_by INT8 REFERENCES users(id);
+CREATE INDEX todos_completed_by ON todos(completed_by);
+CREATE INDEX todos_project_id ON todos(project_id);
+CREATE INDEX projects_team_id ON projects(team_id);
@@ -0,0 +1,2 @@
+ALTER TABLE projects DROP CONSTRAINT projects_team_id_fkey;
+ALTER TABLE teams DROP COLUMN archived_at;
@@ -0,0 +1,22 @@
+-- Archived teams are read only and hidden from the team selector
+ALTER TABLE teams ADD COLUMN archived_at TIMESTAMPTZ;
+
+-- projects.team_id was never a foreign key, existing orphans are reported instead of failing
+-- the migration: the constraint is enforced for new rows and only validated once they are gone
+ALTER TABLE projects ADD CONSTRAINT projects_team_id_fkey
+  FOREIGN KEY (team_id) REFERENCES teams(id) NOT VALID;
+
+DO $$
+DECLARE
+  orphans TEXT;
+BEGIN
+  SELECT string_agg(p.uuid || ' (team ' || p.team_id || ', #' || p.projectid || ')', ', ')
+    INTO orphans
+    FROM projects p
+    WHERE NOT EXISTS (SELECT 1 FROM teams t WHERE t.id = p.team_id);
+  IF orphans IS NULL THEN
+    ALTER TABLE projects VALIDATE CONSTRAINT projects_team_id_fkey;
+  ELSE
+    RAISE WARNING 'Projects without a team, projects_team_id_fkey is left NOT VALID: %', orphans;
+  END IF;
+END $$;
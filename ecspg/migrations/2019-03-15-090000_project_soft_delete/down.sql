@@ -0,0 +1,3 @@
+DROP INDEX projects_deleted_at;
+ALTER TABLE todos DROP COLUMN deleted_at;
+ALTER TABLE projects DROP COLUMN deleted_at;
@@ -0,0 +1,5 @@
+-- Deleted projects go to the trash of their team first, with their todos,
+-- they are purged for good once they have been there for the retention period
+ALTER TABLE projects ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE todos ADD COLUMN deleted_at TIMESTAMPTZ;
+CREATE INDEX projects_deleted_at ON projects(deleted_at) WHERE deleted_at IS NOT NULL;
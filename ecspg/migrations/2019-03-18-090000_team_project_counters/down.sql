@@ -0,0 +1,3 @@
+DROP TABLE team_project_counters;
+CREATE INDEX projects_team_id ON projects(team_id);
+ALTER TABLE projects DROP CONSTRAINT projects_team_id_projectid_key;
@@ -0,0 +1,32 @@
+-- Projects used to be numbered by reading the highest number of the team and adding one,
+-- concurrent adds could end up with the same number. Duplicates are renumbered after the
+-- highest number of their team, the first project created keeping the original one.
+WITH numbered AS (
+  SELECT uuid, team_id,
+    ROW_NUMBER() OVER (PARTITION BY team_id, projectid ORDER BY start_date NULLS LAST, uuid) AS dup,
+    MAX(projectid) OVER (PARTITION BY team_id) AS top
+  FROM projects
+), renumbered AS (
+  SELECT uuid, top + ROW_NUMBER() OVER (PARTITION BY team_id ORDER BY uuid) AS projectid
+  FROM numbered
+  WHERE dup > 1
+)
+UPDATE projects p SET projectid = r.projectid
+FROM renumbered r
+WHERE p.uuid = r.uuid;
+
+ALTER TABLE projects ADD CONSTRAINT projects_team_id_projectid_key UNIQUE (team_id, projectid);
+DROP INDEX projects_team_id;
+
+-- The last project number given out per team, allocated with an upsert so it is atomic
+CREATE TABLE team_project_counters (
+  team_id INT8 PRIMARY KEY REFERENCES teams(id),
+  last_projectid INT8 NOT NULL
+);
+GRANT SELECT ON team_project_counters TO ecs_read;
+GRANT SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES ON team_project_counters TO ecs_write;
+
+INSERT INTO team_project_counters (team_id, last_projectid)
+  SELECT p.team_id, MAX(p.projectid) FROM projects p
+  JOIN teams t ON t.id = p.team_id
+  GROUP BY p.team_id;
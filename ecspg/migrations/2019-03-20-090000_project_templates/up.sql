@@ -0,0 +1,2 @@
+-- Templates are projects of the team which are only ever cloned, they are left out of the project list
+ALTER TABLE projects ADD COLUMN is_template BOOLEAN NOT NULL DEFAULT FALSE;
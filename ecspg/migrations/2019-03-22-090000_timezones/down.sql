@@ -0,0 +1,2 @@
+ALTER TABLE teams DROP COLUMN timezone;
+ALTER TABLE user_meta DROP COLUMN timezone;
@@ -0,0 +1,4 @@
+-- IANA names like Europe/Budapest, dates are entered and shown in the timezone of the user,
+-- falling back to the one of the team and then to UTC
+ALTER TABLE user_meta ADD COLUMN timezone TEXT;
+ALTER TABLE teams ADD COLUMN timezone TEXT;
@@ -0,0 +1,5 @@
+DROP TABLE todo_events;
+UPDATE todos SET completed_at = created_at WHERE completed_at IS NULL;
+ALTER TABLE todos ALTER COLUMN completed_at SET DEFAULT NOW();
+ALTER TABLE todos ALTER COLUMN completed_at SET NOT NULL;
+ALTER TABLE todos DROP COLUMN created_at;
@@ -0,0 +1,34 @@
+-- completed_at defaulted to NOW() at insert and so held the creation time of most todos,
+-- it moves to created_at and completed_at is only set while a todo is completed
+ALTER TABLE todos ADD COLUMN created_at TIMESTAMPTZ;
+UPDATE todos SET created_at = completed_at;
+ALTER TABLE todos ALTER COLUMN created_at SET NOT NULL;
+ALTER TABLE todos ALTER COLUMN created_at SET DEFAULT NOW();
+ALTER TABLE todos ALTER COLUMN completed_at DROP NOT NULL;
+ALTER TABLE todos ALTER COLUMN completed_at DROP DEFAULT;
+UPDATE todos SET completed_at = NULL, completed_by = NULL WHERE NOT completed;
+-- toggling records completed_by together with completed_at, a completed todo without it was
+-- never toggled that way and its completed_at is the creation time
+UPDATE todos SET completed_at = NULL WHERE completed AND completed_by IS NULL;
+
+-- Every change of the state of a todo, with the user who made it
+CREATE TABLE todo_events (
+  id BIGSERIAL PRIMARY KEY,
+  todo_id INT8 NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  event TEXT NOT NULL CHECK (event IN ('created', 'completed', 'reopened')),
+  user_id INT8 REFERENCES users(id),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX todo_events_todo_id ON todo_events(todo_id);
+
+-- the history known so far
+INSERT INTO todo_events (todo_id, event, created_at)
+SELECT id, 'created', created_at FROM todos;
+-- completions are only known when they were recorded, none is made up from creation times
+INSERT INTO todo_events (todo_id, event, user_id, created_at)
+SELECT id, 'completed', completed_by, completed_at FROM todos
+WHERE completed AND completed_at IS NOT NULL;
+
+GRANT SELECT ON todo_events TO ecs_read;
+GRANT SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES ON todo_events TO ecs_write;
+GRANT ALL PRIVILEGES ON ALL SEQUENCES IN SCHEMA public TO ecs_write;
@@ -0,0 +1,3 @@
+-- The member of the team a todo is assigned to, the contact fields are about the subject of the todo
+ALTER TABLE todos ADD COLUMN assignee_id INT8 REFERENCES users(id);
+CREATE INDEX todos_assignee_id ON todos(assignee_id);
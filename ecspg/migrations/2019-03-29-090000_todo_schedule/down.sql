@@ -0,0 +1,3 @@
+DROP INDEX todos_open_due_at;
+ALTER TABLE todos DROP COLUMN priority;
+ALTER TABLE todos DROP COLUMN due_at;
@@ -0,0 +1,4 @@
+-- When a todo is due and how urgent it is, priorities go from 0 (low) to 3 (urgent)
+ALTER TABLE todos ADD COLUMN due_at TIMESTAMPTZ;
+ALTER TABLE todos ADD COLUMN priority INT2 NOT NULL DEFAULT 1 CHECK (priority BETWEEN 0 AND 3);
+CREATE INDEX todos_open_due_at ON todos(due_at) WHERE NOT completed AND deleted_at IS NULL;
@@ -0,0 +1,2 @@
+DROP INDEX todos_project_position;
+ALTER TABLE todos DROP COLUMN position;
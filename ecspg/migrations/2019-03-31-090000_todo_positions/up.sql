@@ -0,0 +1,11 @@
+-- The manual order of the todos of a project, positions are spaced out so that moving a todo
+-- only has to update that one row
+ALTER TABLE todos ADD COLUMN position INT8;
+UPDATE todos SET position = ordered.rank * 1024
+FROM (
+  SELECT id, ROW_NUMBER() OVER (PARTITION BY project_id ORDER BY title, id) AS rank
+  FROM todos
+) AS ordered
+WHERE todos.id = ordered.id;
+ALTER TABLE todos ALTER COLUMN position SET NOT NULL;
+CREATE INDEX todos_project_position ON todos(project_id, position);
@@ -0,0 +1,2 @@
+ALTER TABLE todos DROP COLUMN checklist_mode;
+DROP TABLE todo_items;
@@ -0,0 +1,18 @@
+-- The checklist of a todo, done in order of position
+CREATE TABLE todo_items (
+  id BIGSERIAL PRIMARY KEY,
+  todo_id INT8 NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  title TEXT NOT NULL,
+  completed BOOLEAN NOT NULL DEFAULT FALSE,
+  position INT8 NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX todo_items_todo_id ON todo_items(todo_id, position);
+
+-- 'derive' completes the todo with its last item, 'gate' only keeps it open while items are open
+ALTER TABLE todos ADD COLUMN checklist_mode TEXT NOT NULL DEFAULT 'derive'
+  CHECK (checklist_mode IN ('derive', 'gate'));
+
+GRANT SELECT ON todo_items TO ecs_read;
+GRANT SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES ON todo_items TO ecs_write;
+GRANT ALL PRIVILEGES ON ALL SEQUENCES IN SCHEMA public TO ecs_write;
@@ -0,0 +1,16 @@
+-- The discussion of a project, on the project itself or on one of its todos
+CREATE TABLE comments (
+  id BIGSERIAL PRIMARY KEY,
+  project_id UUID NOT NULL REFERENCES projects(uuid) ON DELETE CASCADE,
+  todo_id INT8 REFERENCES todos(id) ON DELETE CASCADE,
+  user_id INT8 NOT NULL REFERENCES users(id),
+  body TEXT NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  edited_at TIMESTAMPTZ
+);
+CREATE INDEX comments_project_id ON comments(project_id, created_at);
+CREATE INDEX comments_todo_id ON comments(todo_id, created_at);
+
+GRANT SELECT ON comments TO ecs_read;
+GRANT SELECT, INSERT, UPDATE, DELETE, TRUNCATE, REFERENCES ON comments TO ecs_write;
+GRANT ALL PRIVILEGES ON ALL SEQUENCES IN SCHEMA public TO ecs_write;
@@ -0,0 +1,2 @@
+-- The revoked keys stay revoked, their hashes are of no use to the password hash either
+SELECT 1;
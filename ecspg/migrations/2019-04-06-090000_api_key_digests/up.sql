@@ -0,0 +1,3 @@
+-- Keys are hashed with SHA-256 from now on, 64 hex digits. The ones hashed with the password
+-- hash can't be verified anymore and have to be replaced by a rotation or a new key.
+UPDATE api_keys SET revoked_at = now() WHERE revoked_at IS NULL AND length(key_hash) <> 64;
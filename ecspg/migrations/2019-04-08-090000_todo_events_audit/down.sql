@@ -0,0 +1,5 @@
+DELETE FROM todo_events WHERE event = 'deleted';
+ALTER TABLE todo_events DROP CONSTRAINT todo_events_event_check;
+ALTER TABLE todo_events ADD CONSTRAINT todo_events_event_check
+  CHECK (event IN ('created', 'completed', 'reopened'));
+GRANT UPDATE, DELETE, TRUNCATE, REFERENCES ON todo_events TO ecs_write;
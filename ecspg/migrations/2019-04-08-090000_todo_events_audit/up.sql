@@ -0,0 +1,8 @@
+-- todo_events is an audit trail, rows are only ever added to it. They still go with their todo
+-- when a project is purged, referential actions run as the owner of the table.
+REVOKE UPDATE, DELETE, TRUNCATE, REFERENCES ON todo_events FROM ecs_write;
+
+-- Todos are deleted softly with todos.deleted_at, the deletion is recorded like other changes
+ALTER TABLE todo_events DROP CONSTRAINT todo_events_event_check;
+ALTER TABLE todo_events ADD CONSTRAINT todo_events_event_check
+  CHECK (event IN ('created', 'completed', 'reopened', 'deleted'));
@@ -0,0 +1 @@
+DROP VIEW project_statuses;
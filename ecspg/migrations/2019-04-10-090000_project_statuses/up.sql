@@ -0,0 +1,21 @@
+-- The todo counts and the status of every project, the one place the status rules live.
+-- Projects are `upcoming` before their start, `finished` once they have todos and all of them
+-- are completed, `overdue` after their end otherwise, and `active` in between.
+CREATE VIEW project_statuses AS
+SELECT p.uuid AS project_id,
+  COALESCE(t.total, 0) AS total_todos,
+  COALESCE(t.done, 0) AS done_todos,
+  CASE
+    WHEN p.start_date > now() THEN 'upcoming'
+    WHEN t.total > 0 AND t.total = t.done THEN 'finished'
+    WHEN p.end_date < now() THEN 'overdue'
+    ELSE 'active'
+  END AS status
+FROM projects p
+LEFT JOIN (
+  SELECT project_id, COUNT(*) AS total, COUNT(*) FILTER (WHERE completed) AS done
+  FROM todos WHERE deleted_at IS NULL GROUP BY project_id
+) t ON t.project_id = p.uuid;
+
+GRANT SELECT ON project_statuses TO ecs_read;
+GRANT SELECT ON project_statuses TO ecs_write;
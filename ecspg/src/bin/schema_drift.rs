@@ -0,0 +1,441 @@
+//! Compares the tables and the `joinable!` lines of `src/schema.rs` with a migrated database.
+//!
+//! Run it against a database with every migration applied:
+//! > cargo run -p ecspg --bin schema_drift
+//! It takes `DATABASE_URL` from the environment or `.env`, lists every difference
+//! and exits with a non zero status should there be any.
+
+#[macro_use]
+extern crate diesel;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::process;
+
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+
+const SCHEMA: &str = include_str!("../schema.rs");
+
+const COLUMNS_SQL: &str = r#"
+SELECT c.table_name::text AS table_name, c.column_name::text AS column_name,
+  c.data_type::text AS data_type, c.is_nullable::text AS is_nullable
+FROM information_schema.columns c
+JOIN information_schema.tables t
+  ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+-- views are left out of schema.rs by print-schema as well
+WHERE c.table_schema = 'public' AND t.table_type = 'BASE TABLE'
+  AND c.table_name NOT LIKE '\_\_diesel%'
+ORDER BY c.table_name, c.ordinal_position
+"#;
+
+/// Single column foreign keys to the primary key of another table, the ones print-schema
+/// writes a `joinable!` for
+const FOREIGN_KEYS_SQL: &str = r#"
+SELECT c.conrelid::regclass::text AS child_table, a.attname::text AS column_name,
+  c.confrelid::regclass::text AS parent_table
+FROM pg_constraint c
+JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = c.conkey[1]
+WHERE c.contype = 'f' AND c.connamespace = 'public'::regnamespace
+  AND array_length(c.conkey, 1) = 1
+  AND EXISTS (
+    SELECT 1 FROM pg_index i
+    WHERE i.indrelid = c.confrelid AND i.indisprimary AND i.indkey::int2[] = c.confkey
+  )
+"#;
+
+#[derive(QueryableByName, Debug)]
+struct DbForeignKey {
+    #[sql_type = "Text"]
+    child_table: String,
+    #[sql_type = "Text"]
+    column_name: String,
+    #[sql_type = "Text"]
+    parent_table: String,
+}
+
+#[derive(QueryableByName, Debug)]
+struct DbColumn {
+    #[sql_type = "Text"]
+    table_name: String,
+    #[sql_type = "Text"]
+    column_name: String,
+    #[sql_type = "Text"]
+    data_type: String,
+    #[sql_type = "Text"]
+    is_nullable: String,
+}
+
+/// A column as `information_schema` describes it: its data type and whether it is nullable
+type Column = (String, bool);
+type Tables = BTreeMap<String, BTreeMap<String, Column>>;
+
+/// The `information_schema.columns.data_type` a diesel sql type is stored as
+fn pg_type(diesel_type: &str) -> String {
+    match diesel_type {
+        "Int2" | "SmallInt" => "smallint",
+        "Int4" | "Integer" => "integer",
+        "Int8" | "BigInt" => "bigint",
+        "Float4" | "Float" => "real",
+        "Float8" | "Double" => "double precision",
+        "Bool" => "boolean",
+        "Text" => "text",
+        "Varchar" => "character varying",
+        "Timestamptz" => "timestamp with time zone",
+        "Timestamp" => "timestamp without time zone",
+        "Date" => "date",
+        "Uuid" => "uuid",
+        "Json" => "json",
+        "Jsonb" => "jsonb",
+        "Bytea" | "Binary" => "bytea",
+        "Numeric" => "numeric",
+        other => return other.to_lowercase(),
+    }
+    .to_string()
+}
+
+/// Reads the `table!` blocks of the schema, as written by `diesel print-schema`
+fn declared(schema: &str) -> Tables {
+    let mut res = Tables::new();
+    let mut table: Option<String> = None;
+    for line in schema.lines() {
+        let line = line.trim();
+        match table.clone() {
+            None => {
+                if line.ends_with('{') && line.contains('(') && !line.starts_with("table!") {
+                    let name = line.split_whitespace().next().unwrap_or_default();
+                    table = Some(name.to_string());
+                    res.entry(name.to_string()).or_insert_with(BTreeMap::new);
+                }
+            }
+            Some(name) => {
+                if line == "}" {
+                    table = None;
+                } else if let Some(pos) = line.find("->") {
+                    let column = line[..pos].trim().to_string();
+                    let tp = line[pos + 2..].trim().trim_end_matches(',');
+                    let (tp, nullable) = if tp.starts_with("Nullable<") && tp.ends_with('>') {
+                        (&tp["Nullable<".len()..tp.len() - 1], true)
+                    } else {
+                        (tp, false)
+                    };
+                    if let Some(columns) = res.get_mut(&name) {
+                        columns.insert(column, (pg_type(tp), nullable));
+                    }
+                }
+            }
+        }
+    }
+    res
+}
+
+/// A `joinable!(child -> parent (column))` line
+type Joinable = (String, String, String);
+
+/// Reads the `joinable!` lines of the schema
+fn declared_joinables(schema: &str) -> BTreeSet<Joinable> {
+    let mut res = BTreeSet::new();
+    for line in schema.lines() {
+        let line = line.trim();
+        if !line.starts_with("joinable!(") || !line.ends_with("));") {
+            continue;
+        }
+        let inner = &line["joinable!(".len()..line.len() - "));".len()];
+        let mut arrow = inner.splitn(2, "->");
+        let child = arrow.next().unwrap_or_default().trim();
+        let mut rest = arrow.next().unwrap_or_default().splitn(2, '(');
+        let parent = rest.next().unwrap_or_default().trim();
+        let column = rest.next().unwrap_or_default().trim();
+        res.insert((child.to_string(), parent.to_string(), column.to_string()));
+    }
+    res
+}
+
+/// The `joinable!` lines print-schema writes for the foreign keys. Two tables referring to each
+/// other more than once, in either direction, get none: diesel could not tell which key to join
+/// on. Neither do tables referring to themselves.
+fn expected_joinables(foreign_keys: &[Joinable]) -> BTreeSet<Joinable> {
+    let pair = |fk: &Joinable| {
+        if fk.0 < fk.1 {
+            (fk.0.clone(), fk.1.clone())
+        } else {
+            (fk.1.clone(), fk.0.clone())
+        }
+    };
+    foreign_keys
+        .iter()
+        .filter(|fk| fk.0 != fk.1)
+        .filter(|fk| {
+            foreign_keys
+                .iter()
+                .filter(|other| pair(other) == pair(fk))
+                .count()
+                == 1
+        })
+        .cloned()
+        .collect()
+}
+
+fn migrated_foreign_keys(conn: &PgConnection) -> QueryResult<Vec<Joinable>> {
+    let rows: Vec<DbForeignKey> = diesel::sql_query(FOREIGN_KEYS_SQL).load(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.child_table, row.parent_table, row.column_name))
+        .collect())
+}
+
+/// Every `joinable!` line schema.rs is missing or should not have, one line each
+fn joinable_drift(declared: &BTreeSet<Joinable>, expected: &BTreeSet<Joinable>) -> Vec<String> {
+    let describe = |jn: &Joinable| format!("joinable!({} -> {} ({}))", jn.0, jn.1, jn.2);
+    let mut res = Vec::new();
+    for jn in declared.difference(expected) {
+        res.push(format!("{} is in schema.rs only", describe(jn)));
+    }
+    for jn in expected.difference(declared) {
+        res.push(format!("{} is missing from schema.rs", describe(jn)));
+    }
+    res
+}
+
+fn migrated(conn: &PgConnection) -> QueryResult<Tables> {
+    let rows: Vec<DbColumn> = diesel::sql_query(COLUMNS_SQL).load(conn)?;
+    let mut res = Tables::new();
+    for row in rows {
+        res.entry(row.table_name)
+            .or_insert_with(BTreeMap::new)
+            .insert(row.column_name, (row.data_type, row.is_nullable == "YES"));
+    }
+    Ok(res)
+}
+
+fn describe(col: &Column) -> String {
+    if col.1 {
+        format!("{} null", col.0)
+    } else {
+        format!("{} not null", col.0)
+    }
+}
+
+/// Every difference between the declared and the migrated tables, one line each
+fn drift(declared: &Tables, migrated: &Tables) -> Vec<String> {
+    let mut res = Vec::new();
+    for (table, columns) in declared {
+        let db_columns = match migrated.get(table) {
+            Some(db_columns) => db_columns,
+            None => {
+                res.push(format!("table {} is in schema.rs only", table));
+                continue;
+            }
+        };
+        for (column, col) in columns {
+            match db_columns.get(column) {
+                None => res.push(format!("column {}.{} is in schema.rs only", table, column)),
+                Some(db_col) if db_col != col => res.push(format!(
+                    "column {}.{} is {} in schema.rs but {} in the database",
+                    table,
+                    column,
+                    describe(col),
+                    describe(db_col)
+                )),
+                Some(_) => {}
+            }
+        }
+        for column in db_columns.keys() {
+            if !columns.contains_key(column) {
+                res.push(format!(
+                    "column {}.{} is in the database only",
+                    table, column
+                ));
+            }
+        }
+    }
+    for table in migrated.keys() {
+        if !declared.contains_key(table) {
+            res.push(format!("table {} is in the database only", table));
+        }
+    }
+    res
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let conn = PgConnection::establish(&database_url)
+        .unwrap_or_else(|e| panic!("Error connecting to {}: {}", database_url, e));
+    let db_tables = migrated(&conn).expect("Reading information_schema failed");
+    let foreign_keys = migrated_foreign_keys(&conn).expect("Reading pg_constraint failed");
+    let mut differences = drift(&declared(SCHEMA), &db_tables);
+    differences.extend(joinable_drift(
+        &declared_joinables(SCHEMA),
+        &expected_joinables(&foreign_keys),
+    ));
+    if differences.is_empty() {
+        println!("schema.rs matches the database");
+        return;
+    }
+    for diff in &differences {
+        println!("{}", diff);
+    }
+    eprintln!(
+        "{} difference(s) between schema.rs and the database, run `diesel print-schema` after the migrations",
+        differences.len()
+    );
+    process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+table! {
+    users (id) {
+        id -> Int8,
+        uuid -> Uuid,
+    }
+}
+
+table! {
+    todos (id) {
+        id -> Int8,
+        title -> Text,
+        completed_by -> Nullable<Int8>,
+        assignee_id -> Nullable<Int8>,
+        priority -> Int2,
+    }
+}
+
+joinable!(todo_items -> todos (todo_id));
+"#;
+
+    fn columns(cols: &[(&str, &str, bool)]) -> BTreeMap<String, Column> {
+        cols.iter()
+            .map(|(name, tp, nullable)| (name.to_string(), (tp.to_string(), *nullable)))
+            .collect()
+    }
+
+    fn sample_tables() -> Tables {
+        let mut res = Tables::new();
+        res.insert(
+            "users".to_string(),
+            columns(&[("id", "bigint", false), ("uuid", "uuid", false)]),
+        );
+        res.insert(
+            "todos".to_string(),
+            columns(&[
+                ("id", "bigint", false),
+                ("title", "text", false),
+                ("completed_by", "bigint", true),
+                ("assignee_id", "bigint", true),
+                ("priority", "smallint", false),
+            ]),
+        );
+        res
+    }
+
+    fn joinable(child: &str, parent: &str, column: &str) -> Joinable {
+        (child.to_string(), parent.to_string(), column.to_string())
+    }
+
+    #[test]
+    fn reads_declared_tables() {
+        assert_eq!(declared(SAMPLE), sample_tables());
+    }
+
+    #[test]
+    fn matching_tables_have_no_drift() {
+        assert!(drift(&declared(SAMPLE), &sample_tables()).is_empty());
+    }
+
+    #[test]
+    fn reports_every_difference() {
+        let mut db = sample_tables();
+        db.remove("users");
+        db.insert("comments".to_string(), columns(&[("id", "bigint", false)]));
+        let todos = db.get_mut("todos").unwrap();
+        todos.remove("title");
+        todos.insert(
+            "due_at".to_string(),
+            ("timestamp with time zone".to_string(), true),
+        );
+        todos.insert("priority".to_string(), ("smallint".to_string(), true));
+        todos.insert("assignee_id".to_string(), ("integer".to_string(), true));
+        assert_eq!(
+            drift(&declared(SAMPLE), &db),
+            vec![
+                "column todos.assignee_id is bigint null in schema.rs but integer null in the database",
+                "column todos.priority is smallint not null in schema.rs but smallint null in the database",
+                "column todos.title is in schema.rs only",
+                "column todos.due_at is in the database only",
+                "table users is in schema.rs only",
+                "table comments is in the database only",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_declared_joinables() {
+        let expected: BTreeSet<Joinable> = vec![joinable("todo_items", "todos", "todo_id")]
+            .into_iter()
+            .collect();
+        assert_eq!(declared_joinables(SAMPLE), expected);
+    }
+
+    #[test]
+    fn expects_joinables_the_way_print_schema_writes_them() {
+        let foreign_keys = vec![
+            joinable("todo_items", "todos", "todo_id"),
+            // two keys between the same tables, no joinable for either
+            joinable("todos", "users", "completed_by"),
+            joinable("todos", "users", "assignee_id"),
+            // the same, with the keys pointing in both directions
+            joinable("teams", "users", "user_id"),
+            joinable("users", "teams", "team_id"),
+            // a table referring to itself
+            joinable("comments", "comments", "parent_id"),
+        ];
+        let expected: BTreeSet<Joinable> = vec![joinable("todo_items", "todos", "todo_id")]
+            .into_iter()
+            .collect();
+        assert_eq!(expected_joinables(&foreign_keys), expected);
+    }
+
+    #[test]
+    fn reports_joinable_differences() {
+        let declared: BTreeSet<Joinable> = vec![
+            joinable("todo_items", "todos", "todo_id"),
+            joinable("todos", "users", "completed_by"),
+        ]
+        .into_iter()
+        .collect();
+        let expected: BTreeSet<Joinable> = vec![
+            joinable("todo_items", "todos", "todo_id"),
+            joinable("comments", "todos", "todo_id"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            joinable_drift(&declared, &expected),
+            vec![
+                "joinable!(todos -> users (completed_by)) is in schema.rs only",
+                "joinable!(comments -> todos (todo_id)) is missing from schema.rs",
+            ]
+        );
+    }
+
+    /// print-schema only joins tables it declares, by columns they have
+    #[test]
+    fn schema_joinables_use_declared_columns() {
+        let tables = declared(SCHEMA);
+        let joinables = declared_joinables(SCHEMA);
+        assert!(!joinables.is_empty());
+        for (child, parent, column) in joinables.iter() {
+            assert!(tables.contains_key(parent), "{} is not declared", parent);
+            let columns = tables
+                .get(child)
+                .unwrap_or_else(|| panic!("{} is not declared", child));
+            assert!(columns.contains_key(column), "{}.{}", child, column);
+        }
+    }
+}
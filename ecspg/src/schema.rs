@@ -54,33 +54,37 @@ table! {
     api_keys (id) {
         id -> Int8,
         team_id -> Int8,
-        api_key -> Text,
+        key_hash -> Text,
         access_control_id -> Int8,
+        name -> Text,
+        prefix -> Text,
+        scope -> Text,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
     }
 }
 
 table! {
-    menus (id) {
+    comments (id) {
         id -> Int8,
-        title -> Text,
-        links -> Nullable<Jsonb>,
-        tag -> Nullable<Jsonb>,
-        access_control_id -> Int8,
+        project_id -> Uuid,
+        todo_id -> Nullable<Int8>,
+        user_id -> Int8,
+        body -> Text,
+        created_at -> Timestamptz,
+        edited_at -> Nullable<Timestamptz>,
     }
 }
 
 table! {
-    organizers (id) {
+    menus (id) {
         id -> Int8,
-        access_control_id -> Int8,
-        user_id -> Int8,
         title -> Text,
-        content -> Text,
-        billing_name -> Text,
-        billing_address -> Text,
-        billing_city -> Text,
-        billing_country -> Text,
-        billing_zip -> Text,
+        links -> Nullable<Jsonb>,
+        tag -> Nullable<Jsonb>,
+        access_control_id -> Int8,
     }
 }
 
@@ -93,6 +97,8 @@ table! {
         content -> Text,
         start_date -> Nullable<Timestamptz>,
         end_date -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        is_template -> Bool,
     }
 }
 
@@ -103,6 +109,22 @@ table! {
     }
 }
 
+table! {
+    team_groups (id) {
+        id -> Int8,
+        team_id -> Int8,
+        access_group_id -> Int8,
+        role -> Text,
+    }
+}
+
+table! {
+    team_project_counters (team_id) {
+        team_id -> Int8,
+        last_projectid -> Int8,
+    }
+}
+
 table! {
     teams (id) {
         id -> Int8,
@@ -115,6 +137,30 @@ table! {
         billing_city -> Text,
         billing_country -> Text,
         billing_zip -> Text,
+        billing_vat -> Nullable<Text>,
+        archived_at -> Nullable<Timestamptz>,
+        timezone -> Nullable<Text>,
+    }
+}
+
+table! {
+    todo_events (id) {
+        id -> Int8,
+        todo_id -> Int8,
+        event -> Text,
+        user_id -> Nullable<Int8>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    todo_items (id) {
+        id -> Int8,
+        todo_id -> Int8,
+        title -> Text,
+        completed -> Bool,
+        position -> Int8,
+        created_at -> Timestamptz,
     }
 }
 
@@ -127,7 +173,15 @@ table! {
         phone -> Nullable<Text>,
         project_id -> Uuid,
         completed -> Bool,
-        completed_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        completed_by -> Nullable<Int8>,
+        deleted_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        assignee_id -> Nullable<Int8>,
+        due_at -> Nullable<Timestamptz>,
+        priority -> Int2,
+        position -> Int8,
+        checklist_mode -> Text,
     }
 }
 
@@ -141,6 +195,7 @@ table! {
         phone -> Text,
         frozen -> Nullable<Text>,
         created_at -> Timestamptz,
+        timezone -> Nullable<Text>,
     }
 }
 
@@ -171,11 +226,19 @@ joinable!(access_rules -> access_control (access_control_id));
 joinable!(access_rules -> access_groups (access_group_id));
 joinable!(api_keys -> access_control (access_control_id));
 joinable!(api_keys -> teams (team_id));
+joinable!(comments -> projects (project_id));
+joinable!(comments -> todos (todo_id));
+joinable!(comments -> users (user_id));
 joinable!(menus -> access_control (access_control_id));
-joinable!(organizers -> access_control (access_control_id));
-joinable!(organizers -> users (user_id));
+joinable!(projects -> teams (team_id));
+joinable!(team_groups -> access_groups (access_group_id));
+joinable!(team_groups -> teams (team_id));
+joinable!(team_project_counters -> teams (team_id));
 joinable!(teams -> access_control (access_control_id));
 joinable!(teams -> users (user_id));
+joinable!(todo_events -> todos (todo_id));
+joinable!(todo_events -> users (user_id));
+joinable!(todo_items -> todos (todo_id));
 joinable!(todos -> projects (project_id));
 joinable!(user_meta -> users (user_id));
 joinable!(user_pwd -> users (user_id));
@@ -187,11 +250,15 @@ allow_tables_to_appear_in_same_query!(
     access_keys,
     access_rules,
     api_keys,
+    comments,
     menus,
-    organizers,
     projects,
     session_tokens,
+    team_groups,
+    team_project_counters,
     teams,
+    todo_events,
+    todo_items,
     todos,
     user_meta,
     user_pwd,
@@ -1,42 +1,74 @@
 window.onload = function () {
-    drawChart();
+    $.getJSON("/team/dashboard/data", function (data) {
+        drawStatusChart(data.statuses);
+        drawWeekChart(data.weeks);
+        listOverdue(data.overdue);
+        listContributors(data.contributors);
+    });
+};
+const statusColors = {
+    active: 'rgba(54, 162, 235, 0.6)',
+    finished: 'rgba(75, 192, 192, 0.6)',
+    overdue: 'rgba(255, 99, 132, 0.6)',
+    upcoming: 'rgba(255, 206, 86, 0.6)'
 };
-function drawChart() {
-    let ctx = $("#myChart");
-    new Chart(ctx, {
+function drawStatusChart(statuses) {
+    new Chart($("#statusChart"), {
+        type: 'doughnut',
+        data: {
+            labels: statuses.map(s => s.status),
+            datasets: [{
+                data: statuses.map(s => s.count),
+                backgroundColor: statuses.map(s => statusColors[s.status])
+            }]
+        }
+    });
+}
+function drawWeekChart(weeks) {
+    if (weeks.length > 0) {
+        $("#thisWeekCount").text(weeks[weeks.length - 1].count);
+    }
+    new Chart($("#weekChart"), {
         type: 'bar',
         data: {
-            labels: ["Red", "Blue", "Yellow", "Green", "Purple", "Orange"],
+            labels: weeks.map(w => w.week),
             datasets: [{
-                label: '# of Votes',
-                data: [12, 19, 3, 5, 2, 3],
-                backgroundColor: [
-                    'rgba(255, 99, 132, 0.2)',
-                    'rgba(54, 162, 235, 0.2)',
-                    'rgba(255, 206, 86, 0.2)',
-                    'rgba(75, 192, 192, 0.2)',
-                    'rgba(153, 102, 255, 0.2)',
-                    'rgba(255, 159, 64, 0.2)'
-                ],
-                borderColor: [
-                    'rgba(255,99,132,1)',
-                    'rgba(54, 162, 235, 1)',
-                    'rgba(255, 206, 86, 1)',
-                    'rgba(75, 192, 192, 1)',
-                    'rgba(153, 102, 255, 1)',
-                    'rgba(255, 159, 64, 1)'
-                ],
+                label: '#',
+                data: weeks.map(w => w.count),
+                backgroundColor: 'rgba(75, 192, 192, 0.2)',
+                borderColor: 'rgba(75, 192, 192, 1)',
                 borderWidth: 1
             }]
         },
         options: {
+            legend: { display: false },
             scales: {
                 yAxes: [{
                     ticks: {
-                        beginAtZero:true
+                        beginAtZero: true,
+                        precision: 0
                     }
                 }]
             }
         }
     });
-}
\ No newline at end of file
+}
+function listOverdue(projects) {
+    let body = $("#overdueProjects");
+    projects.forEach(p => {
+        let row = $("<tr>");
+        row.append($("<td>").append($("<a>").attr("href", "/project/" + p.projectid + "/todo").text(p.title)));
+        row.append($("<td>").text(p.end_date ? p.end_date.substring(0, 10) : ""));
+        row.append($("<td>").text(p.open_todos));
+        body.append(row);
+    });
+}
+function listContributors(contributors) {
+    let body = $("#topContributors");
+    contributors.forEach(c => {
+        let row = $("<tr>");
+        row.append($("<td>").text(c.display));
+        row.append($("<td>").text(c.count));
+        body.append(row);
+    });
+}
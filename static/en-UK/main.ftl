@@ -19,4 +19,8 @@ enter_password_input =
 .placeholder = Password
 invite_code = Invite code
 enter_invite_code_input =
-.placeholder = Enter Invite code
\ No newline at end of file
+.placeholder = Enter Invite code
+projects_by_status = Projects by status
+completed_per_week = Completed todos per week
+overdue_projects = Overdue projects
+top_contributors = Top contributors
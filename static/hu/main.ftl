@@ -1,4 +1,8 @@
 dashboard = Vezérlőpult
 share = Megosztás
 export = Kivitel
-this_week= E hét
\ No newline at end of file
+this_week= E hét
+projects_by_status = Projektek állapot szerint
+completed_per_week = Elvégzett feladatok hetente
+overdue_projects = Lejárt projektek
+top_contributors = Legaktívabb tagok
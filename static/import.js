@@ -0,0 +1,14 @@
+// Reads the chosen CSV file into the list of the import form
+window.onload = function () {
+    document.getElementById('file').addEventListener('change', function (ev) {
+        var file = ev.target.files[0];
+        if (!file) {
+            return;
+        }
+        var reader = new FileReader();
+        reader.onload = function () {
+            document.getElementById('list').value = reader.result;
+        };
+        reader.readAsText(file, 'UTF-8');
+    });
+};
@@ -3,4 +3,44 @@ $('#list-table tbody').on('click', 'tr', function () {
     let lid = $(this).attr("id");
     let oid = lid.match(/listing-(\d*)/)[1];
     window.location.href = '?id='+oid;
-})
\ No newline at end of file
+})
+// manual order of the todos, see project::order
+var dragged = null;
+
+$('#todolist[data-sortable] > li').on('dragstart', function (event) {
+    dragged = this;
+    event.originalEvent.dataTransfer.effectAllowed = 'move';
+    $(this).addClass('list-group-item-secondary');
+});
+
+$('#todolist[data-sortable] > li').on('dragover', function (event) {
+    if (dragged === null || dragged === this) {
+        return;
+    }
+    event.preventDefault();
+    let rect = this.getBoundingClientRect();
+    if (event.originalEvent.clientY < rect.top + rect.height / 2) {
+        $(this).before(dragged);
+    } else {
+        $(this).after(dragged);
+    }
+});
+
+$('#todolist[data-sortable] > li').on('dragend', function () {
+    $(this).removeClass('list-group-item-secondary');
+    if (dragged === null) {
+        return;
+    }
+    let id = $(dragged).data('id');
+    let after = $(dragged).prev('li').data('id');
+    let before = $(dragged).next('li').data('id');
+    dragged = null;
+    if (after === undefined && before === undefined) {
+        return;
+    }
+    let params = after !== undefined ? { id, after } : { id, before };
+    $.post('./todo/order', params)
+        .fail(function () {
+            window.location.reload(true);
+        });
+});
@@ -1,15 +1,49 @@
 window.onload = function() {
   updateCounter();
 };
-var exampleSocket = new WebSocket("wss://ecs.dev.reedwolf.com:3000");
+// changes made by others on the same project, see project::live
+var liveUrl = (window.location.protocol === 'https:' ? 'wss://' : 'ws://') +
+  window.location.host + window.location.pathname.replace(/\/todo\/?$/, '/live');
+var exampleSocket = new WebSocket(liveUrl);
 
 exampleSocket.onopen = function (event) {
 
   exampleSocket.onmessage = function (event) {
     let data = JSON.parse(event.data);
-    var contents = $(`#switch${data.id}`).prop('checked', data.value === true);
-    $(`#switch${data.id}`).prop("disabled", true);
-    setTimeout(() => $(`#switch${data.id}`).prop("disabled", false), 1000);
+    switch (data.event) {
+      case 'toggle':
+        $(`#switch${data.id}`).prop('checked', data.value === true);
+        $(`#switch${data.id}`).prop("disabled", true);
+        setTimeout(() => $(`#switch${data.id}`).prop("disabled", false), 1000);
+        // completed todos are not overdue anymore
+        $(`#todo${data.id} .todo-overdue`).toggle(data.value !== true);
+        break;
+      case 'edit':
+        $(`#todo${data.id} .todo-title`).text(data.title);
+        break;
+      case 'delete':
+        $(`#todo${data.id}`).remove();
+        $(`#deleteModal-${data.id}`).remove();
+        break;
+      case 'move':
+        // only the manually ordered list follows the moves
+        if ($('#todolist[data-sortable]').length) {
+          if (data.after === undefined) {
+            $('#todolist').prepend($(`#todo${data.id}`));
+          } else {
+            $(`#todo${data.after}`).after($(`#todo${data.id}`));
+          }
+        }
+        break;
+      case 'check':
+        $(`#item${data.item}`).prop('checked', data.value === true);
+        break;
+      case 'checklist':
+      case 'add':
+        // new todos are rendered by the server
+        window.location.reload(true);
+        return;
+    }
     updateCounter();
   }
 
@@ -20,28 +54,32 @@ exampleSocket.onopen = function (event) {
 
 function checkboxClicked(id) {
   let value = $(`#switch${id}`).is(':checked');
-  let data = JSON.stringify({
-    id,
-    value
-  });
-  exampleSocket.send(data);
-  var jqxhr = $.post('./todo', { id, value })
-  //   .done(function () {
-  //     exampleSocket.send(id);
-  //   })
+  $.post('./todo', { id, value })
+  .fail(function () {
+    // refused (no permission, the checklist decides) or lost, show the stored state
+    $(`#switch${id}`).prop('checked', !value);
+    window.location.reload(true);
+  })
+}
+
+function itemClicked(todo, item) {
+  let value = $(`#item${item}`).is(':checked');
+  $.post(`./todo/${todo}/items/${item}`, { value })
   .fail(function () {
     window.location.reload(true);
   })
-  //   .always(function () {
-  //     console.log("finished");
-  //   });
 }
 
 function updateCounter() {
   let total = $(".material-switch > input[type='checkbox']").length
   let on = $(".material-switch > input[type='checkbox']:checked").length
-  let perc = Math.floor(on/total*100);
-  $('#completedcounter').html(on+"/"+total+" "+perc+"%");
+  let perc = total ? Math.floor(on/total*100) : 0;
+  $('#todocounter').html(on+"/"+total+" "+perc+"%");
+  // the n/m of the checklist of every todo
+  $('.todo-items-counter').each(function () {
+    let items = $(this).closest('li').find('.todo-item-check');
+    $(this).html(items.filter(':checked').length+"/"+items.length);
+  });
 }
 function textTyped() {
   var value = $("#searchthis").val().toLowerCase();
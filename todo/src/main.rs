@@ -10,6 +10,8 @@ use pretty_env_logger;
 
 use ecslib::modules;
 
+use crate::modules::access::PermissionCheck;
+use crate::modules::user::impersonate::ImpersonationGuard;
 use crate::modules::user::restrict::Restrict;
 
 fn main() {
@@ -49,16 +51,74 @@ fn main() {
             .middleware(SessionStorage::new(
                 CookieSessionBackend::private(&[0; 32]).secure(secure),
             ))
+            .middleware(ImpersonationGuard)
+            .middleware(PermissionCheck)
             .prefix("/project")
             .resource("list", |r| {
                 r.method(Method::GET)
-                    .f(crate::modules::project::list::index)
+                    .with(crate::modules::project::list::index)
             })
             .resource("add", |r| {
                 r.method(Method::GET).f(crate::modules::project::add::index);
                 r.method(Method::POST)
                     .with(crate::modules::project::add::save);
             })
+            .resource("trash", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::delete::trash)
+            })
+            .resource("templates", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::template::index)
+            })
+            .resource("{id}/comments", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::project::comments::add);
+            })
+            .resource("{id}/comments/{cid}", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::project::comments::edit);
+            })
+            .resource("{id}/comments/{cid}/delete", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::project::comments::delete);
+            })
+            .resource("{id}/delete", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::project::delete::delete)
+            })
+            .resource("{id}/template", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::project::template::save_template)
+            })
+            .resource("{id}/clone", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::template::clone_form);
+                r.method(Method::POST)
+                    .with(crate::modules::project::template::save_clone);
+            })
+            .resource("{id}/export.csv", |r| {
+                r.method(Method::GET)
+                    .with(crate::modules::project::export::csv)
+            })
+            .resource("{id}/export.json", |r| {
+                r.method(Method::GET)
+                    .with(crate::modules::project::export::json)
+            })
+            .resource("{id}/import", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::import::index);
+                r.method(Method::POST)
+                    .with(crate::modules::project::import::save);
+            })
+            .resource("{id}/restore", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::project::delete::restore)
+            })
+            .resource("{id}/live", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::live::index)
+            })
             .resource("{id}/todo", |r| {
                 r.method(Method::GET)
                     .f(crate::modules::project::todo::index);
@@ -77,12 +137,28 @@ fn main() {
                 r.method(Method::POST)
                     .with(crate::modules::project::todo_register::save);
             })
+            .resource("{id}/todo/order", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::project::order::reorder);
+            })
             .resource("{id}/todo/{aid}", |r| {
                 r.method(Method::GET)
                     .f(crate::modules::project::todo_register::edit_page);
                 r.method(Method::POST)
                     .with(crate::modules::project::todo_register::save_todo);
             })
+            .resource("{id}/todo/{aid}/items", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::project::checklist::add);
+            })
+            .resource("{id}/todo/{aid}/items/{iid}", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::project::checklist::toggle);
+            })
+            .resource("{id}/todo/{aid}/items/{iid}/delete", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::project::checklist::delete);
+            })
             .resource("{id}/todo/{aid}/delete", |r| {
                 r.method(Method::GET)
                     .f(crate::modules::project::todo_register::delete_todo);
@@ -91,6 +167,10 @@ fn main() {
                 r.method(Method::GET)
                     .f(crate::modules::project::todo_list::index)
             })
+            .resource("{id}", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::view::index)
+            })
             .resource("/", |r| {
                 r.method(Method::GET).f(|_req| {
                     HttpResponse::Found()
@@ -113,6 +193,127 @@ fn main() {
             .middleware(SessionStorage::new(
                 CookieSessionBackend::private(&[0; 32]).secure(secure),
             ))
+            .middleware(ImpersonationGuard)
+            .middleware(PermissionCheck)
+            .prefix("/todo")
+            .resource("mine", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::mine::index)
+            })
+            .resource("overdue", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::project::mine::overdue)
+            })
+            .default_resource(|r| r.method(Method::GET).h(NormalizePath::default())),
+            App::with_state(crate::db::AppState {
+                rdb: raddr.clone(),
+                wdb: waddr.clone(),
+            })
+            .middleware(middleware::Logger::default())
+            .middleware(IdentityService::new(
+                CookieIdentityPolicy::new(&[0; 128])
+                    .name("auth-cookie")
+                    .secure(secure),
+            ))
+            .middleware(Restrict)
+            .middleware(SessionStorage::new(
+                CookieSessionBackend::private(&[0; 32]).secure(secure),
+            ))
+            .middleware(ImpersonationGuard)
+            .middleware(PermissionCheck)
+            .prefix("/team")
+            .resource("select", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::team::select::select);
+                r.method(Method::POST).with(crate::modules::team::set);
+            })
+            .resource("list", |r| {
+                r.method(Method::GET)
+                    .with(crate::modules::team::list::index)
+            })
+            .resource("add", |r| {
+                r.method(Method::GET).f(crate::modules::team::add::index);
+                r.method(Method::POST).with(crate::modules::team::add::save);
+            })
+            .resource("dashboard/data", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::team::dashboard::data)
+            })
+            .resource("dashboard", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::team::dashboard::index)
+            })
+            .resource("{id}/members/{uid}/role", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::team::members::change_role)
+            })
+            .resource("{id}/members/{uid}/remove", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::team::members::remove)
+            })
+            .resource("{id}/members", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::team::members::index);
+                r.method(Method::POST)
+                    .with(crate::modules::team::members::invite);
+            })
+            .resource("{id}/keys/{kid}/rotate", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::team::keys::rotate)
+            })
+            .resource("{id}/keys/{kid}/revoke", |r| {
+                r.method(Method::POST).f(crate::modules::team::keys::revoke)
+            })
+            .resource("{id}/keys", |r| {
+                r.method(Method::GET).f(crate::modules::team::keys::index);
+                r.method(Method::POST)
+                    .with(crate::modules::team::keys::create);
+            })
+            .resource("{id}/owner", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::team::members::transfer)
+            })
+            .resource("{id}/archive", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::team::delete::archive)
+            })
+            .resource("{id}/unarchive", |r| {
+                r.method(Method::POST)
+                    .f(crate::modules::team::delete::unarchive)
+            })
+            .resource("{id}/delete", |r| {
+                r.method(Method::GET).f(crate::modules::team::delete::index);
+                r.method(Method::POST)
+                    .with(crate::modules::team::delete::delete);
+            })
+            .resource("{id}/edit", |r| {
+                r.method(Method::GET).f(crate::modules::team::edit::index);
+                r.method(Method::POST)
+                    .with(crate::modules::team::edit::save);
+            })
+            .resource("/", |r| {
+                r.method(Method::GET).f(|_req| {
+                    HttpResponse::Found()
+                        .header(header::LOCATION, "/team/select")
+                        .finish()
+                })
+            })
+            .default_resource(|r| r.method(Method::GET).h(NormalizePath::default())),
+            App::with_state(crate::db::AppState {
+                rdb: raddr.clone(),
+                wdb: waddr.clone(),
+            })
+            .middleware(middleware::Logger::default())
+            .middleware(IdentityService::new(
+                CookieIdentityPolicy::new(&[0; 128])
+                    .name("auth-cookie")
+                    .secure(secure),
+            ))
+            .middleware(Restrict)
+            .middleware(SessionStorage::new(
+                CookieSessionBackend::private(&[0; 32]).secure(secure),
+            ))
+            .middleware(ImpersonationGuard)
             .prefix("/user")
             .resource("login", |r| {
                 r.method(Method::GET).f(crate::modules::user::login::index);
@@ -126,6 +327,23 @@ fn main() {
                     .with(crate::modules::user::register::save);
             })
             .resource("logout", |r| r.f(crate::modules::user::login::logout))
+            .resource("impersonate/start", |r| {
+                r.method(Method::POST)
+                    .with(crate::modules::user::impersonate::start)
+            })
+            .resource("impersonate/stop", |r| {
+                r.f(crate::modules::user::impersonate::stop)
+            })
+            .resource("impersonate", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::user::impersonate::index)
+            })
+            .resource("settings", |r| {
+                r.method(Method::GET)
+                    .f(crate::modules::user::settings::index);
+                r.method(Method::POST)
+                    .with(crate::modules::user::settings::save);
+            })
             .resource("list", |r| {
                 r.method(Method::GET).f(|_req| {
                     HttpResponse::Found()
@@ -139,6 +357,40 @@ fn main() {
                 wdb: waddr.clone(),
             })
             .middleware(middleware::Logger::default())
+            .middleware(IdentityService::new(
+                CookieIdentityPolicy::new(&[0; 128])
+                    .name("auth-cookie")
+                    .secure(secure),
+            ))
+            .middleware(Restrict)
+            .middleware(SessionStorage::new(
+                CookieSessionBackend::private(&[0; 32]).secure(secure),
+            ))
+            .middleware(ImpersonationGuard)
+            .prefix("/access")
+            .resource("explain", |r| {
+                r.method(Method::GET)
+                    .with(crate::modules::access::explain::index)
+            })
+            .default_resource(|r| r.method(Method::GET).h(NormalizePath::default())),
+            // scripts authenticate with the api keys of a team instead of a login
+            App::with_state(crate::db::AppState {
+                rdb: raddr.clone(),
+                wdb: waddr.clone(),
+            })
+            .middleware(middleware::Logger::default())
+            .prefix("/api")
+            .resource("project/{id}/todos/{aid}/toggle", |r| {
+                r.method(Method::POST).with(crate::modules::api::toggle)
+            })
+            .resource("project/{id}/todos", |r| {
+                r.method(Method::GET).f(crate::modules::api::todos)
+            }),
+            App::with_state(crate::db::AppState {
+                rdb: raddr.clone(),
+                wdb: waddr.clone(),
+            })
+            .middleware(middleware::Logger::default())
             .prefix("/static")
             .handler("/", fs::StaticFiles::new("./static/").unwrap())
             .default_resource(|r| r.method(Method::GET).h(NormalizePath::default())),
@@ -156,6 +408,7 @@ fn main() {
             .middleware(SessionStorage::new(
                 CookieSessionBackend::private(&[0; 32]).secure(secure),
             ))
+            .middleware(ImpersonationGuard)
             .resource("/", |r| {
                 r.method(Method::GET).f(|_req| {
                     HttpResponse::Found()